@@ -6,6 +6,7 @@ extern crate syntex_syntax;
 extern crate toml;
 extern crate env_logger;
 #[macro_use] extern crate clap;
+extern crate rustc_serialize;
 
 extern crate racer;
 
@@ -16,7 +17,21 @@ use racer::util;
 #[cfg(not(test))]
 use racer::core::Match;
 #[cfg(not(test))]
-use racer::util::{getline, path_exists};
+use racer::docs;
+#[cfg(not(test))]
+use racer::references::{self, Reference};
+#[cfg(not(test))]
+use racer::signature::{self, SignatureHelp};
+#[cfg(not(test))]
+use racer::outline::{self, OutlineItem};
+#[cfg(not(test))]
+use racer::symbols;
+#[cfg(not(test))]
+use racer::rename::{self, Edit};
+#[cfg(not(test))]
+use racer::arms;
+#[cfg(not(test))]
+use racer::util::path_exists;
 #[cfg(not(test))]
 use racer::nameres::{do_file_search, do_external_search, PATH_SEP};
 #[cfg(not(test))]
@@ -26,11 +41,16 @@ use std::path::{Path, PathBuf};
 #[cfg(not(test))]
 use std::io::{self, BufRead};
 #[cfg(not(test))]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(not(test))]
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+#[cfg(not(test))]
+use rustc_serialize::json::{self, Json, ToJson};
 
 #[cfg(not(test))]
-fn match_with_snippet_fn(m: Match, session: &core::Session, interface: Interface) {
-    let (linenum, charnum) = scopes::point_to_coords_from_file(&m.filepath, m.point, session).unwrap();
+fn match_with_snippet_fn(m: Match, session: &core::Session, interface: Interface,
+                         encoding: core::Encoding) {
+    let (linenum, charnum) = coords_in_file(&m.filepath, m.point, encoding, session).unwrap();
     if m.matchstr == "" {
         panic!("MATCHSTR is empty - waddup?");
     }
@@ -55,14 +75,14 @@ fn match_with_snippet_fn(m: Match, session: &core::Session, interface: Interface
                         m.filepath.to_str().unwrap(),
                         m.mtype,
                         m.contextstr),
+        Interface::Json =>
+            print_json_record("MATCH", match_to_json(&m, linenum, charnum, Some(snippet), encoding, session)),
     }
 }
 
 #[cfg(not(test))]
-fn match_fn(m: Match, session: &core::Session, interface: Interface) {
-    if let Some((linenum, charnum)) = scopes::point_to_coords_from_file(&m.filepath,
-                                                                        m.point,
-                                                                        session) {
+fn match_fn(m: Match, session: &core::Session, interface: Interface, encoding: core::Encoding) {
+    if let Some((linenum, charnum)) = coords_in_file(&m.filepath, m.point, encoding, session) {
         match interface {
             Interface::Text =>
                 println!("MATCH {},{},{},{},{:?},{}",
@@ -80,6 +100,8 @@ fn match_fn(m: Match, session: &core::Session, interface: Interface) {
                             m.filepath.to_str().unwrap(),
                             m.mtype,
                             m.contextstr),
+            Interface::Json =>
+                print_json_record("MATCH", match_to_json(&m, linenum, charnum, None, encoding, session)),
         }
     } else {
         error!("Could not resolve file coords for match {:?}", m);
@@ -87,30 +109,311 @@ fn match_fn(m: Match, session: &core::Session, interface: Interface) {
 }
 
 #[cfg(not(test))]
-fn complete(cfg: Config, print_type: CompletePrinter) {
-    if cfg.fqn.is_some() {
-        return external_complete(cfg);
+fn match_to_json(m: &Match, linenum: usize, charnum: usize, snippet: Option<String>,
+                 encoding: core::Encoding, session: &core::Session) -> json::Object {
+    let mut obj = json::Object::new();
+    obj.insert("matchstr".to_owned(), m.matchstr.to_json());
+    obj.insert("snippet".to_owned(), snippet.to_json());
+    obj.insert("line".to_owned(), linenum.to_json());
+    obj.insert("column".to_owned(), charnum.to_json());
+    obj.insert("filepath".to_owned(), m.filepath.to_str().unwrap().to_json());
+    obj.insert("mtype".to_owned(), format!("{:?}", m.mtype).to_json());
+    obj.insert("contextstr".to_owned(), m.contextstr.to_json());
+    obj.insert("local".to_owned(), m.local.to_json());
+    obj.insert("docs".to_owned(), m.docs.to_json());
+    // the end of the name, and the extent of the whole item
+    let src = session.load_file(&m.filepath);
+    if m.name_range.1 <= src.len() && m.item_range.1 <= src.len() {
+        let (nameendline, nameendchar) = scopes::point_to_encoded_coords(&src, m.name_range.1, encoding);
+        let (startline, startchar) = scopes::point_to_encoded_coords(&src, m.item_range.0, encoding);
+        let (endline, endchar) = scopes::point_to_encoded_coords(&src, m.item_range.1, encoding);
+        obj.insert("nameendline".to_owned(), nameendline.to_json());
+        obj.insert("nameendcolumn".to_owned(), nameendchar.to_json());
+        obj.insert("startline".to_owned(), startline.to_json());
+        obj.insert("startcolumn".to_owned(), startchar.to_json());
+        obj.insert("endline".to_owned(), endline.to_json());
+        obj.insert("endcolumn".to_owned(), endchar.to_json());
     }
-    complete_by_line_coords(cfg, print_type);
+    obj
 }
 
+/// Prints a single json record on its own line. `kind` is stored under the "type" key and is
+/// one of the record names used by the text interfaces (PREFIX, MATCH, END).
 #[cfg(not(test))]
-fn complete_by_line_coords(cfg: Config,
-                           print_type: CompletePrinter) {
-    // input: linenum, colnum, fname
-    let tb = std::thread::Builder::new().name("searcher".to_owned());
+fn print_json_record(kind: &str, mut obj: json::Object) {
+    obj.insert("type".to_owned(), kind.to_json());
+    println!("{}", Json::Object(obj));
+}
 
-    // PD: this probably sucks for performance, but lots of plugins
-    // end up failing and leaving tmp files around if racer crashes,
-    // so catch the crash.
-    let res = tb.spawn(move || {
-        run_the_complete_fn(&cfg, print_type);
-    }).unwrap();
-    if let Err(e) = res.join() {
-        error!("Search thread paniced: {:?}", e);
+/// The line and column of a point in a file, with the column counted in encoding
+#[cfg(not(test))]
+fn coords_in_file(path: &Path, point: usize, encoding: core::Encoding,
+                  session: &core::Session) -> Option<(usize, usize)> {
+    let src = session.load_file(path);
+    if point > src.len() {
+        return None;
     }
+    Some(scopes::point_to_encoded_coords(&src, point, encoding))
+}
 
-    println!("END");
+#[cfg(not(test))]
+fn print_prefix(start: usize, pos: usize, prefix: &str, interface: Interface) {
+    match interface {
+        Interface::Text =>
+            println!("PREFIX {},{},{}", start, pos, prefix),
+        Interface::TabText =>
+            println!("PREFIX\t{}\t{}\t{}", start, pos, prefix),
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("start".to_owned(), start.to_json());
+            obj.insert("end".to_owned(), pos.to_json());
+            obj.insert("prefix".to_owned(), prefix.to_json());
+            print_json_record("PREFIX", obj);
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn print_type(ty: &core::Ty, interface: Interface) {
+    match interface {
+        Interface::Text =>
+            println!("TYPE {}", ty),
+        Interface::TabText =>
+            println!("TYPE\t{}", ty),
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("typestr".to_owned(), ty.to_string().to_json());
+            print_json_record("TYPE", obj);
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn print_reference(r: &Reference, name: &str, session: &core::Session, interface: Interface,
+                   encoding: core::Encoding) {
+    let (linenum, charnum) = match coords_in_file(&r.filepath, r.point, encoding, session) {
+        Some(coords) => coords,
+        None => {
+            error!("Could not resolve file coords for reference {:?}", r);
+            return;
+        }
+    };
+    match interface {
+        Interface::Text =>
+            println!("REFERENCE {},{},{},{},{:?}",
+                     name, linenum, charnum, r.filepath.to_str().unwrap(), r.kind),
+        Interface::TabText =>
+            println!("REFERENCE\t{}\t{}\t{}\t{}\t{:?}",
+                     name, linenum, charnum, r.filepath.to_str().unwrap(), r.kind),
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("matchstr".to_owned(), name.to_json());
+            obj.insert("line".to_owned(), linenum.to_json());
+            obj.insert("column".to_owned(), charnum.to_json());
+            obj.insert("filepath".to_owned(), r.filepath.to_str().unwrap().to_json());
+            obj.insert("kind".to_owned(), format!("{:?}", r.kind).to_json());
+            print_json_record("REFERENCE", obj);
+        }
+    }
+}
+
+/// Prints a DOC record per line of the docs in the text interfaces, or a single record with all
+/// of them in json.
+#[cfg(not(test))]
+fn print_docs(docs: &str, interface: Interface) {
+    match interface {
+        Interface::Text => {
+            for line in docs.lines() {
+                println!("DOC {}", line);
+            }
+        }
+        Interface::TabText => {
+            for line in docs.lines() {
+                println!("DOC\t{}", line);
+            }
+        }
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("docs".to_owned(), docs.to_json());
+            print_json_record("DOC", obj);
+        }
+    }
+}
+
+/// Prints the signature followed by an ARGUMENT record per argument. The text interface
+/// separates argument fields with ';' since types and docs can contain commas.
+#[cfg(not(test))]
+fn print_signature(sig: &SignatureHelp, interface: Interface) {
+    match interface {
+        Interface::Text => {
+            println!("SIGNATURE {},{},{}", sig.name, sig.active_arg, sig.signature);
+            for arg in &sig.args {
+                println!("ARGUMENT {};{};{}", arg.name, arg.ty, arg.docs);
+            }
+        }
+        Interface::TabText => {
+            println!("SIGNATURE\t{}\t{}\t{}", sig.name, sig.active_arg, sig.signature);
+            for arg in &sig.args {
+                println!("ARGUMENT\t{}\t{}\t{}", arg.name, arg.ty, arg.docs);
+            }
+        }
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("matchstr".to_owned(), sig.name.to_json());
+            obj.insert("active".to_owned(), sig.active_arg.to_json());
+            obj.insert("signature".to_owned(), sig.signature.to_json());
+            obj.insert("docs".to_owned(), sig.docs.to_json());
+            print_json_record("SIGNATURE", obj);
+            for arg in &sig.args {
+                let mut obj = json::Object::new();
+                obj.insert("name".to_owned(), arg.name.to_json());
+                obj.insert("ty".to_owned(), arg.ty.to_json());
+                obj.insert("docs".to_owned(), arg.docs.to_json());
+                print_json_record("ARGUMENT", obj);
+            }
+        }
+    }
+}
+
+/// Prints an OUTLINE record for the item followed by its children. The text interfaces are flat,
+/// with the nesting given by the depth field; json nests children under "children".
+#[cfg(not(test))]
+fn print_outline_item(item: &OutlineItem, depth: usize, src: &str, interface: Interface,
+                      encoding: core::Encoding) {
+    let (linenum, charnum) = scopes::point_to_encoded_coords(src, item.point, encoding);
+    let (startline, startchar) = scopes::point_to_encoded_coords(src, item.start, encoding);
+    let (endline, endchar) = scopes::point_to_encoded_coords(src, item.end, encoding);
+    let visibility = if item.public { "Public" } else { "Private" };
+    match interface {
+        Interface::Text =>
+            println!("OUTLINE {},{:?},{},{},{},{},{},{},{},{}",
+                     depth, item.mtype, visibility, linenum, charnum,
+                     startline, startchar, endline, endchar, item.name),
+        Interface::TabText =>
+            println!("OUTLINE\t{}\t{:?}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                     depth, item.mtype, visibility, linenum, charnum,
+                     startline, startchar, endline, endchar, item.name),
+        Interface::Json => {
+            print_json_record("OUTLINE", outline_item_to_json(item, src, encoding));
+            return;
+        }
+    }
+    for child in &item.children {
+        print_outline_item(child, depth + 1, src, interface, encoding);
+    }
+}
+
+#[cfg(not(test))]
+fn outline_item_to_json(item: &OutlineItem, src: &str, encoding: core::Encoding) -> json::Object {
+    let (linenum, charnum) = scopes::point_to_encoded_coords(src, item.point, encoding);
+    let (startline, startchar) = scopes::point_to_encoded_coords(src, item.start, encoding);
+    let (endline, endchar) = scopes::point_to_encoded_coords(src, item.end, encoding);
+    let mut obj = json::Object::new();
+    obj.insert("matchstr".to_owned(), item.name.to_json());
+    obj.insert("mtype".to_owned(), format!("{:?}", item.mtype).to_json());
+    obj.insert("public".to_owned(), item.public.to_json());
+    obj.insert("line".to_owned(), linenum.to_json());
+    obj.insert("column".to_owned(), charnum.to_json());
+    obj.insert("startline".to_owned(), startline.to_json());
+    obj.insert("startcolumn".to_owned(), startchar.to_json());
+    obj.insert("endline".to_owned(), endline.to_json());
+    obj.insert("endcolumn".to_owned(), endchar.to_json());
+    let children = item.children.iter()
+        .map(|child| Json::Object(outline_item_to_json(child, src, encoding)))
+        .collect();
+    obj.insert("children".to_owned(), Json::Array(children));
+    obj
+}
+
+#[cfg(not(test))]
+fn print_edit(edit: &Edit, session: &core::Session, interface: Interface, encoding: core::Encoding) {
+    let src = session.load_file(&edit.filepath);
+    let (linenum, charnum) = scopes::point_to_encoded_coords(&src, edit.start, encoding);
+    let (endlinenum, endcharnum) = scopes::point_to_encoded_coords(&src, edit.end, encoding);
+    match interface {
+        Interface::Text =>
+            println!("EDIT {},{},{},{},{},{}", linenum, charnum, endlinenum, endcharnum,
+                     edit.filepath.to_str().unwrap(), edit.replacement),
+        Interface::TabText =>
+            println!("EDIT\t{}\t{}\t{}\t{}\t{}\t{}", linenum, charnum, endlinenum, endcharnum,
+                     edit.filepath.to_str().unwrap(), edit.replacement),
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("line".to_owned(), linenum.to_json());
+            obj.insert("column".to_owned(), charnum.to_json());
+            obj.insert("endline".to_owned(), endlinenum.to_json());
+            obj.insert("endcolumn".to_owned(), endcharnum.to_json());
+            obj.insert("start".to_owned(), edit.start.to_json());
+            obj.insert("end".to_owned(), edit.end.to_json());
+            obj.insert("filepath".to_owned(), edit.filepath.to_str().unwrap().to_json());
+            obj.insert("replacement".to_owned(), edit.replacement.to_json());
+            print_json_record("EDIT", obj);
+        }
+    }
+}
+
+/// Prints a missing match arm, at the point where the arms should be inserted
+#[cfg(not(test))]
+fn print_arm(arm: &str, edit: &Edit, session: &core::Session, interface: Interface,
+             encoding: core::Encoding) {
+    let src = session.load_file(&edit.filepath);
+    let (linenum, charnum) = scopes::point_to_encoded_coords(&src, edit.start, encoding);
+    match interface {
+        Interface::Text =>
+            println!("ARM {},{},{},{}", linenum, charnum, edit.filepath.to_str().unwrap(), arm),
+        Interface::TabText =>
+            println!("ARM\t{}\t{}\t{}\t{}", linenum, charnum, edit.filepath.to_str().unwrap(), arm),
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("line".to_owned(), linenum.to_json());
+            obj.insert("column".to_owned(), charnum.to_json());
+            obj.insert("start".to_owned(), edit.start.to_json());
+            obj.insert("filepath".to_owned(), edit.filepath.to_str().unwrap().to_json());
+            obj.insert("arm".to_owned(), arm.to_json());
+            print_json_record("ARM", obj);
+        }
+    }
+}
+
+/// Prints why a command refused to do what it was asked
+#[cfg(not(test))]
+fn print_error(message: &str, interface: Interface) {
+    match interface {
+        Interface::Text =>
+            println!("ERROR {}", message),
+        Interface::TabText =>
+            println!("ERROR\t{}", message),
+        Interface::Json => {
+            let mut obj = json::Object::new();
+            obj.insert("message".to_owned(), message.to_json());
+            print_json_record("ERROR", obj);
+        }
+    }
+}
+
+#[cfg(not(test))]
+fn print_end(interface: Interface) {
+    match interface {
+        Interface::Text | Interface::TabText => println!("END"),
+        Interface::Json => print_json_record("END", json::Object::new()),
+    }
+}
+
+#[cfg(not(test))]
+fn complete<'c>(cfg: Config, print_type: CompletePrinter, cache: &'c core::FileCache<'c>) {
+    if cfg.fqn.is_some() {
+        return external_complete(cfg, cache);
+    }
+    complete_by_line_coords(cfg, print_type, cache);
+}
+
+#[cfg(not(test))]
+fn complete_by_line_coords<'c>(cfg: Config,
+                               print_type: CompletePrinter,
+                               cache: &'c core::FileCache<'c>) {
+    // input: linenum, colnum, fname
+    run_the_complete_fn(&cfg, print_type, cache);
+    print_end(cfg.interface);
 }
 
 #[cfg(not(test))]
@@ -121,107 +424,359 @@ enum CompletePrinter {
 
 #[cfg(not(test))]
 fn cache_file_contents_from_stdin<'a>(file: &PathBuf, cache: &'a core::FileCache<'a>) {
+    cache.cache_file_contents(file, read_buffer_from_stdin());
+}
+
+/// Reads text from stdin up to (and swallowing) a 0x04 terminator
+#[cfg(not(test))]
+fn read_buffer_from_stdin() -> String {
     let stdin = io::stdin();
 
     let mut rawbytes = Vec::new();
     stdin.lock().read_until(0x04, &mut rawbytes).unwrap();
+    if rawbytes.last() == Some(&0x04) {
+        rawbytes.pop();
+    }
+
+    String::from_utf8(rawbytes).unwrap()
+}
+
+/// open-buffer / replace-buffer: caches the contents sent on stdin for the path, so that later
+/// queries see the unsaved buffer instead of the file on disk
+#[cfg(not(test))]
+fn cache_buffer<'c>(m: &ArgMatches, interface: Interface, cache: &'c core::FileCache<'c>) {
+    let path = PathBuf::from(m.value_of("path").unwrap());
+    cache.cache_file_contents(&path, read_buffer_from_stdin());
+    print_end(interface);
+}
+
+/// edit-buffer: replaces a line/char range of the buffer with the text sent on stdin
+#[cfg(not(test))]
+fn edit_buffer<'c>(m: &ArgMatches, interface: Interface, encoding: core::Encoding,
+                   cache: &'c core::FileCache<'c>) {
+    let path = PathBuf::from(m.value_of("path").unwrap());
+    let startline = value_t_or_exit!(m.value_of("startline"), usize);
+    let startchar = value_t_or_exit!(m.value_of("startchar"), usize);
+    let endline = value_t_or_exit!(m.value_of("endline"), usize);
+    let endchar = value_t_or_exit!(m.value_of("endchar"), usize);
+    let text = read_buffer_from_stdin();
+
+    let range = {
+        let src = cache.load_file(&path);
+        let nlines = src.split('\n').count();
+        if startline == 0 || endline == 0 || startline > nlines || endline > nlines {
+            None
+        } else {
+            Some((scopes::encoded_coords_to_point(&src, startline, startchar, encoding),
+                  scopes::encoded_coords_to_point(&src, endline, endchar, encoding)))
+        }
+    };
+    match range {
+        Some((start, end)) if cache.edit_file_contents(&path, start, end, &text) => {}
+        _ => {
+            let message = format!("invalid range {}:{}-{}:{} for {}",
+                                  startline, startchar, endline, endchar, path.display());
+            error!("edit-buffer: {}", message);
+            print_error(&message, interface);
+        }
+    }
+    print_end(interface);
+}
 
-    let buf = String::from_utf8(rawbytes).unwrap();
-    cache.cache_file_contents(file, buf);
+/// close-buffer: drops the unsaved contents, later queries read the file from disk again
+#[cfg(not(test))]
+fn close_buffer<'c>(m: &ArgMatches, interface: Interface, cache: &'c core::FileCache<'c>) {
+    cache.uncache_file(Path::new(m.value_of("path").unwrap()));
+    print_end(interface);
+}
+
+/// Creates the session for a query, reading the query path from substitute_file and each
+/// --substitute path from its tmpfile
+#[cfg(not(test))]
+fn new_session<'c>(cfg: &Config, query_path: &Path, substitute_file: &Path,
+                   cache: &'c core::FileCache<'c>) -> core::Session<'c> {
+    let mut session = core::Session::from_path(cache, query_path, substitute_file);
+    for &(ref path, ref tmpfile) in &cfg.substitutes {
+        session.substitute_file(path, tmpfile);
+    }
+    session
 }
 
+/// Parses the `path=tmpfile` values of --substitute
 #[cfg(not(test))]
-fn run_the_complete_fn(cfg: &Config, print_type: CompletePrinter) {
+fn parse_substitutes(values: Vec<&str>) -> Vec<(PathBuf, PathBuf)> {
+    values.into_iter().filter_map(|value| {
+        let mut parts = value.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(path), Some(tmpfile)) if !path.is_empty() && !tmpfile.is_empty() =>
+                Some((PathBuf::from(path), PathBuf::from(tmpfile))),
+            _ => {
+                error!("ignoring --substitute {}, expected path=tmpfile", value);
+                None
+            }
+        }
+    }).collect()
+}
+
+#[cfg(not(test))]
+fn run_the_complete_fn<'c>(cfg: &Config, print_type: CompletePrinter, cache: &'c core::FileCache<'c>) {
     let fn_path = &*cfg.fn_name.as_ref().unwrap();
     let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
 
-    let cache = core::FileCache::new();
-    let session = core::Session::from_path(&cache, fn_path, substitute_file);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
 
     if substitute_file.to_str() == Some("-") {
-        cache_file_contents_from_stdin(&substitute_file, &cache);
+        cache_file_contents_from_stdin(&substitute_file, cache);
     }
 
     let src = session.load_file(fn_path);
-    let line = &getline(substitute_file, cfg.linenum, &session);
-    let (start, pos) = util::expand_ident(line, cfg.charnum);
-    match cfg.interface {
-        Interface::Text =>
-            println!("PREFIX {},{},{}", start, pos, &line[start..pos]),
-        Interface::TabText =>
-            println!("PREFIX\t{}\t{}\t{}", start, pos, &line[start..pos]),
-    }
+    let point = cfg.point_in(&src);
+    print_prefix_at(&src, point, &cfg);
 
-    let point = scopes::coords_to_point(&src, cfg.linenum, cfg.charnum);
-
-    for m in core::complete_from_file(&src, fn_path, point, &session) {
+    let matches = if cfg.fuzzy {
+        core::fuzzy_complete_from_file(&src, fn_path, point, &session)
+    } else {
+        core::complete_from_file(&src, fn_path, point, &session)
+    };
+    for m in matches {
+        let m = if cfg.docs { docs::fill_docs(m, &session) } else { m };
         match print_type {
-            CompletePrinter::Normal => match_fn(m, &session, cfg.interface),
-            CompletePrinter::WithSnippets => match_with_snippet_fn(m, &session, cfg.interface),
+            CompletePrinter::Normal => match_fn(m, &session, cfg.interface, cfg.encoding),
+            CompletePrinter::WithSnippets => match_with_snippet_fn(m, &session, cfg.interface, cfg.encoding),
         };
     }
 }
 
 
 #[cfg(not(test))]
-fn external_complete(cfg: Config) {
+fn external_complete<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
     // input: a command line string passed in
     let p: Vec<&str> = cfg.fqn.as_ref().unwrap().split("::").collect();
     let cwd = Path::new(".");
-    let cache = core::FileCache::new();
-    let session = core::Session::from_path(&cache, &cwd, &cwd);
+    let session = new_session(&cfg, &cwd, &cwd, cache);
 
     for m in do_file_search(p[0], &Path::new(".")) {
         if p.len() == 1 {
-            match_fn(m, &session, cfg.interface);
+            let m = if cfg.docs { docs::fill_docs(m, &session) } else { m };
+            match_fn(m, &session, cfg.interface, cfg.encoding);
         } else {
-            for m in do_external_search(&p[1..], &m.filepath, m.point,
-                                        core::SearchType::StartsWith,
+            let search_type = if cfg.fuzzy {
+                core::SearchType::Fuzzy
+            } else {
+                core::SearchType::StartsWith
+            };
+            for m in do_external_search(&p[1..], &m.filepath, m.point, search_type,
                                         core::Namespace::BothNamespaces, &session) {
-                match_fn(m, &session, cfg.interface);
+                let m = if cfg.docs { docs::fill_docs(m, &session) } else { m };
+                match_fn(m, &session, cfg.interface, cfg.encoding);
             }
         }
     }
 }
 
 #[cfg(not(test))]
-fn prefix(cfg: Config) {
+fn prefix<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
     let fn_path = &*cfg.fn_name.as_ref().unwrap();
     let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
-    let cache = core::FileCache::new();
-    let session = core::Session::from_path(&cache, fn_path, substitute_file);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
 
     if substitute_file.to_str() == Some("-") {
-        cache_file_contents_from_stdin(&substitute_file, &cache);
+        cache_file_contents_from_stdin(&substitute_file, cache);
     }
 
     // print the start, end, and the identifier prefix being matched
-    let line = &getline(fn_path, cfg.linenum, &session);
-    let (start, pos) = util::expand_ident(line, cfg.charnum);
-    match cfg.interface {
-        Interface::Text =>
-            println!("PREFIX {},{},{}", start, pos, &line[start..pos]),
-        Interface::TabText =>
-            println!("PREFIX\t{}\t{}\t{}", start, pos, &line[start..pos]),
+    let src = session.load_file(fn_path);
+    print_prefix_at(&src, cfg.point_in(&src), &cfg);
+}
+
+/// Prints the identifier ending at point, with its start and end as columns of its line
+#[cfg(not(test))]
+fn print_prefix_at(src: &str, point: usize, cfg: &Config) {
+    let (start, pos) = util::expand_ident(src, point);
+    let (_, startcol) = scopes::point_to_encoded_coords(src, start, cfg.encoding);
+    let (_, poscol) = scopes::point_to_encoded_coords(src, pos, cfg.encoding);
+    print_prefix(startcol, poscol, &src[start..pos], cfg.interface);
+}
+
+#[cfg(not(test))]
+fn find_definition<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
+    let fn_path = &*cfg.fn_name.as_ref().unwrap();
+    let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
+
+    if substitute_file.to_str() == Some("-") {
+        cache_file_contents_from_stdin(&substitute_file, cache);
     }
+
+    let src = session.load_file(fn_path);
+    let pos = cfg.point_in(&src);
+
+    core::find_definition(&src, fn_path, pos, &session).map(|m| match_fn(m, &session, cfg.interface, cfg.encoding));
+    print_end(cfg.interface);
 }
 
+/// doc: prints the definition of the item at a point followed by its docs
 #[cfg(not(test))]
-fn find_definition(cfg: Config) {
+fn doc<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
     let fn_path = &*cfg.fn_name.as_ref().unwrap();
     let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
-    let cache = core::FileCache::new();
-    let session = core::Session::from_path(&cache, fn_path, substitute_file);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
 
     if substitute_file.to_str() == Some("-") {
-        cache_file_contents_from_stdin(&substitute_file, &cache);
+        cache_file_contents_from_stdin(&substitute_file, cache);
     }
 
     let src = session.load_file(fn_path);
-    let pos = scopes::coords_to_point(&src, cfg.linenum, cfg.charnum);
+    let pos = cfg.point_in(&src);
 
-    core::find_definition(&src, fn_path, pos, &session).map(|m| match_fn(m, &session, cfg.interface));
-    println!("END");
+    if let Some(m) = core::find_definition(&src, fn_path, pos, &session) {
+        let docs = m.docs.clone();
+        match_fn(m, &session, cfg.interface, cfg.encoding);
+        print_docs(&docs, cfg.interface);
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn fill_match<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
+    let fn_path = &*cfg.fn_name.as_ref().unwrap();
+    let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
+
+    if substitute_file.to_str() == Some("-") {
+        cache_file_contents_from_stdin(&substitute_file, cache);
+    }
+
+    let src = session.load_file(fn_path);
+    let pos = cfg.point_in(&src);
+
+    if let Some(missing) = arms::fill_match(&src, fn_path, pos, &session) {
+        for arm in &missing.arms {
+            print_arm(arm, &missing.edit, &session, cfg.interface, cfg.encoding);
+        }
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn type_at<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
+    let fn_path = &*cfg.fn_name.as_ref().unwrap();
+    let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
+
+    if substitute_file.to_str() == Some("-") {
+        cache_file_contents_from_stdin(&substitute_file, cache);
+    }
+
+    let src = session.load_file(fn_path);
+    let pos = cfg.point_in(&src);
+
+    // the type, followed by the item defining it (whose contextstr is its signature)
+    if let Some(ty) = core::find_type_at(&src, fn_path, pos, &session) {
+        print_type(&ty, cfg.interface);
+        if let Some(m) = ty.defining_match() {
+            match_fn(m.clone(), &session, cfg.interface, cfg.encoding);
+        }
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn find_references<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
+    let fn_path = &*cfg.fn_name.as_ref().unwrap();
+    let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
+
+    if substitute_file.to_str() == Some("-") {
+        cache_file_contents_from_stdin(&substitute_file, cache);
+    }
+
+    let src = session.load_file(fn_path);
+    let pos = cfg.point_in(&src);
+
+    if let Some((def, refs)) = references::find_references(&src, fn_path, pos, &session) {
+        for r in &refs {
+            print_reference(r, &def.matchstr, &session, cfg.interface, cfg.encoding);
+        }
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn signature_help<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
+    let fn_path = &*cfg.fn_name.as_ref().unwrap();
+    let substitute_file = cfg.substitute_file.as_ref().unwrap_or(fn_path);
+    let session = new_session(&cfg, fn_path, substitute_file, cache);
+
+    if substitute_file.to_str() == Some("-") {
+        cache_file_contents_from_stdin(&substitute_file, cache);
+    }
+
+    let src = session.load_file(fn_path);
+    let pos = cfg.point_in(&src);
+
+    if let Some(sig) = signature::find_signature_help(&src, fn_path, pos, &session) {
+        print_signature(&sig, cfg.interface);
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn outline<'c>(m: &ArgMatches, cfg: Config, cache: &'c core::FileCache<'c>) {
+    // Config only reads the path when there are coordinates, so take it from the args
+    let fn_path = PathBuf::from(m.value_of("path").unwrap());
+    let substitute_file = m.value_of("substitute_file").map_or(fn_path.clone(), PathBuf::from);
+    let session = new_session(&cfg, &fn_path, &substitute_file, cache);
+
+    if substitute_file.to_str() == Some("-") {
+        cache_file_contents_from_stdin(&substitute_file, cache);
+    }
+
+    let src = session.load_file(&fn_path);
+    for item in &outline::outline(&fn_path, &session) {
+        print_outline_item(item, 0, &src, cfg.interface, cfg.encoding);
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn workspace_symbols<'c>(m: &ArgMatches, cfg: Config, cache: &'c core::FileCache<'c>) {
+    let query = m.value_of("query").unwrap();
+    let fn_path = PathBuf::from(m.value_of("path").unwrap());
+    let session = new_session(&cfg, &fn_path, &fn_path, cache);
+
+    for m in symbols::workspace_symbols(query, &fn_path, m.is_present("deps"), &session) {
+        match_fn(m, &session, cfg.interface, cfg.encoding);
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn rename<'c>(m: &ArgMatches, cfg: Config, cache: &'c core::FileCache<'c>) {
+    let newname = m.value_of("newname").unwrap();
+    let fn_path = &*cfg.fn_name.as_ref().unwrap();
+    let session = new_session(&cfg, fn_path, fn_path, cache);
+
+    let src = session.load_file(fn_path);
+    let pos = cfg.point_in(&src);
+
+    match rename::rename(&src, fn_path, pos, newname, &session) {
+        Ok(edits) => for edit in &edits {
+            print_edit(edit, &session, cfg.interface, cfg.encoding);
+        },
+        Err(e) => print_error(&e.to_string(), cfg.interface)
+    }
+    print_end(cfg.interface);
+}
+
+#[cfg(not(test))]
+fn lsp() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(e) = racer::lsp::run(&mut stdin.lock(), &mut stdout.lock()) {
+        error!("lsp server stopped: {}", e);
+        std::process::exit(1);
+    }
 }
 
 #[cfg(not(test))]
@@ -257,11 +812,11 @@ fn check_rust_src_env_var() {
 }
 
 #[cfg(not(test))]
-fn daemon(cfg: Config) {
+fn daemon<'c>(cfg: Config, cache: &'c core::FileCache<'c>) {
     let mut input = String::new();
     while let Ok(n) = io::stdin().read_line(&mut input) {
-        // '\n' == 1
-        if n == 1 {
+        // '\n' == 1, and 0 means stdin was closed
+        if n <= 1 {
             break;
         }
         // We add the setting NoBinaryName because in daemon mode we won't be passed the preceeding
@@ -269,14 +824,35 @@ fn daemon(cfg: Config) {
         let cli = build_cli().setting(AppSettings::NoBinaryName);
         let matches = match cfg.interface {
             Interface::Text => cli.get_matches_from(input.trim_right().split_whitespace()),
-            Interface::TabText => cli.get_matches_from(input.trim_right().split('\t'))
+            Interface::TabText => cli.get_matches_from(input.trim_right().split('\t')),
+            Interface::Json => match parse_json_args(&input) {
+                Some(args) => cli.get_matches_from(args),
+                None => {
+                    error!("daemon: expected a json array of strings, got |{}|", input.trim_right());
+                    input.clear();
+                    continue;
+                }
+            }
         };
-        run(matches, cfg.interface);
+        // the cache lives for the whole session; drop anything that has
+        // changed on disk since the last query
+        cache.invalidate_stale_files();
+        run(matches, cfg.interface, cfg.encoding, cache);
 
         input.clear();
     }
 }
 
+/// Reads a daemon command line in the json interface, e.g. `["complete", "12", "5", "lib.rs"]`
+#[cfg(not(test))]
+fn parse_json_args(input: &str) -> Option<Vec<String>> {
+    let args = match Json::from_str(input.trim_right()) {
+        Ok(Json::Array(args)) => args,
+        _ => return None
+    };
+    args.iter().map(|arg| arg.as_string().map(ToOwned::to_owned)).collect()
+}
+
 #[cfg(not(test))]
 #[derive(Copy, Clone)]
 enum Interface {
@@ -284,6 +860,8 @@ enum Interface {
     TabText, // Machine-readable format.  This is basically the same as Text, except that all field
              // separators are replaced with tabs.
              // In `deamon` mode tabs are also used to delimit command arguments.
+    Json,    // One json object per output record, with the record name under the "type" key.
+             // In `daemon` mode each command is read as a json array of strings.
 }
 
 #[cfg(not(test))]
@@ -299,12 +877,37 @@ struct Config {
     charnum: usize,
     fn_name: Option<PathBuf>,
     substitute_file: Option<PathBuf>,
+    substitutes: Vec<(PathBuf, PathBuf)>,
+    fuzzy: bool,
+    docs: bool,                     // whether completions get their docs, which costs a file read each
+    point: Option<usize>,           // --point, given instead of linenum and charnum
+    encoding: core::Encoding,
     interface: Interface,
 }
 
+#[cfg(not(test))]
+impl Config {
+    /// The point the command is about, from --point or from the line and column
+    fn point_in(&self, src: &str) -> usize {
+        match self.point {
+            Some(point) => std::cmp::min(point, src.len()),
+            None => scopes::encoded_coords_to_point(src, self.linenum, self.charnum, self.encoding)
+        }
+    }
+}
+
 #[cfg(not(test))]
 impl<'a> From<&'a ArgMatches<'a, 'a>> for Config {
     fn from(m: &'a ArgMatches) -> Self {
+        // --point replaces the line, column and path
+        if m.is_present("point") {
+            let (path, point) = point_arg_value(m);
+            return Config {
+                point: Some(point),
+                fn_name: Some(path),
+                ..Default::default()
+            };
+        }
         // We check for charnum because it's the second argument, which means more than just
         // an FQN was used (i.e. racer complete <linenum> <charnum> <fn_name> [substitute_file])
         if m.is_present("charnum") {
@@ -326,6 +929,40 @@ impl<'a> From<&'a ArgMatches<'a, 'a>> for Config {
     }
 }
 
+/// --point, given instead of the line, column and path positional args
+#[cfg(not(test))]
+fn point_arg<'n, 'l, 'h, 'g, 'p, 'r>() -> Arg<'n, 'l, 'h, 'g, 'p, 'r> {
+    Arg::with_name("point")
+        .long("point")
+        .takes_value(true)
+        .value_name("path:byte-offset")
+        .help("The point as a file and a byte offset into it, given instead of <linenum>, \
+               <charnum> and <path>. Use --substitute for an unsaved buffer")
+}
+
+/// The path and offset of --point, split at the last colon since paths can contain colons.
+/// Exits with a usage error if the value isn't a path and a number, like value_t_or_exit!
+#[cfg(not(test))]
+fn point_arg_value(m: &ArgMatches) -> (PathBuf, usize) {
+    let value = m.value_of("point").unwrap();
+    let parsed = value.rfind(':').and_then(|n| {
+        value[n+1..].parse::<usize>().ok()
+            .and_then(|point| if n > 0 { Some((PathBuf::from(&value[..n]), point)) } else { None })
+    });
+    match parsed {
+        Some(parsed) => parsed,
+        None => {
+            println!("{} '{}' isn't a valid value for --point <path:byte-offset>\n\n{}\n\n\
+                      Please re-run with {} for more information",
+                     clap::Format::Error("error:"),
+                     clap::Format::Warning(value),
+                     m.usage(),
+                     clap::Format::Good("--help"));
+            std::process::exit(1);
+        }
+    }
+}
+
 #[cfg(not(test))]
 fn build_cli<'a, 'b, 'c, 'd, 'e, 'f>() -> App<'a, 'b, 'c, 'd, 'e, 'f> {
     // we use the more verbose "Builder Pattern" to create the CLI because it's a littel faster
@@ -343,75 +980,292 @@ fn build_cli<'a, 'b, 'c, 'd, 'e, 'f>() -> App<'a, 'b, 'c, 'd, 'e, 'f> {
             .takes_value(true)
             .possible_value("text")
             .possible_value("tab-text")
+            .possible_value("json")
             .value_name("mode")
             .help("Interface mode"))
+        .arg(Arg::with_name("encoding")
+            .long("encoding")
+            .takes_value(true)
+            .global(true)
+            .possible_value("bytes")
+            .possible_value("chars")
+            .possible_value("utf16")
+            .value_name("unit")
+            .help("What columns count, in both the input and output coordinates. Defaults to \
+                   bytes; LSP clients use utf16"))
+        .arg(Arg::with_name("substitute")
+            .long("substitute")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .global(true)
+            .value_name("path=tmpfile")
+            .help("Read path from tmpfile instead, e.g. an unsaved buffer. May be repeated: \
+                   --substitute=src/foo.rs=/tmp/foo.rs --substitute=src/bar.rs=/tmp/bar.rs"))
         .subcommand(SubCommand::with_name("complete")
             .about("performs completion and returns matches")
             // We set an explicit usage string here, instead of letting `clap` write one due to
             // using a single arg for multiple purposes
-            .usage("racer complete <fqn>\n\t\
-                    racer complete <linenum> <charnum> <path> [substitute_file]")
+            .usage("racer complete [--fuzzy] [--docs] <fqn>\n\t\
+                    racer complete [--fuzzy] [--docs] <linenum> <charnum> <path> [substitute_file]\n\t\
+                    racer complete [--fuzzy] [--docs] --point <path:byte-offset>")
             // Next we make it an error to run without any args
             .setting(AppSettings::ArgRequiredElseHelp)
+            .arg(Arg::with_name("fuzzy")
+                .long("fuzzy")
+                .help("Match abbreviations of names, e.g. hmap for HashMap, best matches first"))
+            .arg(Arg::with_name("docs")
+                .long("docs")
+                .help("Fill in the docs of each match (the json interface's docs field)"))
+            .arg(point_arg())
             // Because we want a single arg to play two roles and be compatible with previous
             // racer releases, we have to be a little hacky here...
             //
             // We start by making 'fqn' the first positional arg, which will hold this dual value
             // of either an FQN as it says, or secretly a line-number
             .arg(Arg::with_name("fqn")
-                .help("complete with a fully-qualified-name (e.g. std::io::)"))
+                .help("complete with a fully-qualified-name (e.g. std::io::)")
+                .conflicts_with("point"))
             .arg(Arg::with_name("charnum")
                 .help("The char number to search for matches")
-                .requires("path"))
+                .requires("path")
+                .conflicts_with("point"))
             .arg(Arg::with_name("path")
-                .help("The path to search for name to match"))
+                .help("The path to search for name to match")
+                .conflicts_with("point"))
             .arg(Arg::with_name("substitute_file")
-                .help("An optional substitute file"))
+                .help("An optional substitute file")
+                .conflicts_with("point"))
             // 'linenum' **MUST** be last (or have the highest index so that it's never actually
             // used by the user, but still appears in the help text)
             .arg(Arg::with_name("linenum")
-                .help("The line number at which to find the match")))
+                .help("The line number at which to find the match")
+                .conflicts_with("point")))
         .subcommand(SubCommand::with_name("daemon")
             .about("start a process that receives the above commands via stdin"))
+        .subcommand(SubCommand::with_name("lsp")
+            .about("start a Language Server Protocol server on stdin/stdout"))
         .subcommand(SubCommand::with_name("find-definition")
             .about("finds the definition of a function")
+            .arg(point_arg())
             .arg(Arg::with_name("linenum")
                 .help("The line number at which to find the match")
+                .conflicts_with("point")
                 .required(true))
             .arg(Arg::with_name("charnum")
                 .help("The char number at which to find the match")
+                .conflicts_with("point")
                 .required(true))
             .arg(Arg::with_name("path")
                 .help("The path to search for name to match")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("substitute_file")
+                .help("An optional substitute file")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("find-references")
+            .about("finds the reads, writes and definition of the item at a point within the crate")
+            .arg(point_arg())
+            .arg(Arg::with_name("linenum")
+                .help("The line number at which to find the item")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("charnum")
+                .help("The char number at which to find the item")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path to search for name to match")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("substitute_file")
+                .help("An optional substitute file")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("signature-help")
+            .about("prints the signature of the call enclosing a point and its active argument")
+            .arg(point_arg())
+            .arg(Arg::with_name("linenum")
+                .help("The line number inside the call's parentheses")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("charnum")
+                .help("The char number inside the call's parentheses")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of the file containing the call")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("substitute_file")
+                .help("An optional substitute file")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("doc")
+            .about("prints the definition of the item at a point followed by its doc comments")
+            .arg(point_arg())
+            .arg(Arg::with_name("linenum")
+                .help("The line number of the item's name")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("charnum")
+                .help("The char number of the item's name")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of the file containing the name")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("substitute_file")
+                .help("An optional substitute file")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("type-at")
+            .about("prints the type of the expression at a point and the item defining it")
+            .arg(point_arg())
+            .arg(Arg::with_name("linenum")
+                .help("The line number of the expression")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("charnum")
+                .help("The char number of the expression")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of the file containing the expression")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("substitute_file")
+                .help("An optional substitute file")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("fill-match")
+            .about("prints the arms that the match at a point is missing, if it matches on an enum")
+            .arg(point_arg())
+            .arg(Arg::with_name("linenum")
+                .help("The line number of the match keyword, or of a point in its arms")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("charnum")
+                .help("The char number of the match keyword, or of a point in its arms")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of the file containing the match")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("substitute_file")
+                .help("An optional substitute file")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("outline")
+            .about("lists the items of a file as a tree, with their kind, extent and visibility")
+            .arg(Arg::with_name("path")
+                .help("The path of the file to outline")
                 .required(true))
             .arg(Arg::with_name("substitute_file")
                 .help("An optional substitute file")))
+        .subcommand(SubCommand::with_name("workspace-symbols")
+            .about("searches the names of the items in a crate, best matches first")
+            .arg(Arg::with_name("deps")
+                .long("deps")
+                .help("Also search the crates named by extern crate that cargo can find"))
+            .arg(Arg::with_name("query")
+                .help("The text to look for in item names, ignoring case")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of any file in the crate")
+                .required(true)))
+        .subcommand(SubCommand::with_name("rename")
+            .about("prints the edits that rename the item at a point across the crate, without \
+                    changing any files. Prints an ERROR record if the new name would collide")
+            .arg(point_arg())
+            // the new name comes first, so that it's the only positional arg with --point
+            .arg(Arg::with_name("newname")
+                .help("The new name")
+                .required(true))
+            .arg(Arg::with_name("linenum")
+                .help("The line number of the name to rename")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("charnum")
+                .help("The char number of the name to rename")
+                .conflicts_with("point")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of the file containing the name")
+                .conflicts_with("point")
+                .required(true)))
         .subcommand(SubCommand::with_name("prefix")
+            .arg(point_arg())
             .arg(Arg::with_name("linenum")
                 .help("The line number at which to find the match")
+                .conflicts_with("point")
                 .required(true))
             .arg(Arg::with_name("charnum")
                 .help("The char number at which to find the match")
+                .conflicts_with("point")
                 .required(true))
             .arg(Arg::with_name("path")
                 .help("The path to search for the match to prefix")
+                .conflicts_with("point")
                 .required(true)))
         .subcommand(SubCommand::with_name("complete-with-snippet")
             .about("performs completion and returns more detailed matches")
-            .usage("racer complete-with-snippet <fqn>\n\t\
-                    racer complete-with-snippet <linenum> <charnum> <path> [substitute_file]")
+            .usage("racer complete-with-snippet [--fuzzy] [--docs] <fqn>\n\t\
+                    racer complete-with-snippet [--fuzzy] [--docs] <linenum> <charnum> <path> [substitute_file]\n\t\
+                    racer complete-with-snippet [--fuzzy] [--docs] --point <path:byte-offset>")
             .setting(AppSettings::ArgRequiredElseHelp)
+            .arg(Arg::with_name("fuzzy")
+                .long("fuzzy")
+                .help("Match abbreviations of names, e.g. hmap for HashMap, best matches first"))
+            .arg(Arg::with_name("docs")
+                .long("docs")
+                .help("Fill in the docs of each match (the json interface's docs field)"))
+            .arg(point_arg())
             .arg(Arg::with_name("fqn")
-                .help("complete with a fully-qualified-name (e.g. std::io::)"))
+                .help("complete with a fully-qualified-name (e.g. std::io::)")
+                .conflicts_with("point"))
             .arg(Arg::with_name("charnum")
                 .help("The char number to search for matches")
-                .requires("path"))
+                .requires("path")
+                .conflicts_with("point"))
             .arg(Arg::with_name("path")
-                .help("The path to search for name to match"))
+                .help("The path to search for name to match")
+                .conflicts_with("point"))
             .arg(Arg::with_name("substitute_file")
-                .help("An optional substitute file"))
+                .help("An optional substitute file")
+                .conflicts_with("point"))
             .arg(Arg::with_name("linenum")
-                .help("The line number at which to find the match")))
+                .help("The line number at which to find the match")
+                .conflicts_with("point")))
+        .subcommand(SubCommand::with_name("open-buffer")
+            .about("caches an unsaved buffer, read from stdin up to a 0x04 byte (for use with daemon)")
+            .arg(Arg::with_name("path")
+                .help("The path of the file being edited")
+                .required(true)))
+        .subcommand(SubCommand::with_name("replace-buffer")
+            .about("replaces the contents of an open buffer, read from stdin up to a 0x04 byte")
+            .arg(Arg::with_name("path")
+                .help("The path of the file being edited")
+                .required(true)))
+        .subcommand(SubCommand::with_name("edit-buffer")
+            .about("replaces a range of an open buffer with text read from stdin up to a 0x04 byte")
+            .arg(Arg::with_name("startline")
+                .help("The line number at which the replaced range starts")
+                .required(true))
+            .arg(Arg::with_name("startchar")
+                .help("The char number at which the replaced range starts")
+                .required(true))
+            .arg(Arg::with_name("endline")
+                .help("The line number at which the replaced range ends")
+                .required(true))
+            .arg(Arg::with_name("endchar")
+                .help("The char number at which the replaced range ends")
+                .required(true))
+            .arg(Arg::with_name("path")
+                .help("The path of the file being edited")
+                .required(true)))
+        .subcommand(SubCommand::with_name("close-buffer")
+            .about("forgets an open buffer, so the file is read from disk again")
+            .arg(Arg::with_name("path")
+                .help("The path of the file being edited")
+                .required(true)))
         .after_help("For more information about a specific command try 'racer <command> --help'")
 }
 
@@ -424,24 +1278,74 @@ fn main() {
     let interface = match matches.value_of("interface") {
             Some("text") => Interface::Text,
             Some("tab-text") => Interface::TabText,
+            Some("json") => Interface::Json,
             _ => Interface::Text,
         };
-    run(matches, interface);
+    let cache = core::FileCache::new();
+    run(matches, interface, core::Encoding::Bytes, &cache);
+}
+
+#[cfg(not(test))]
+fn run<'c>(m: ArgMatches, interface: Interface, encoding: core::Encoding,
+           cache: &'c core::FileCache<'c>) {
+    // PD: this probably sucks for performance, but lots of plugins
+    // end up failing and leaving tmp files around if racer crashes,
+    // so catch the crash. A panicking command mustn't end the daemon
+    // either. The cache is borrowed rather than moved into a search
+    // thread so that the daemon can keep it warm between queries.
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_command(m, interface, encoding, cache);
+    }));
+    if let Err(e) = res {
+        error!("Search paniced: {:?}", e);
+        // the command may have been part way through updating the cache
+        cache.reset();
+        print_end(interface);
+    }
 }
 
 #[cfg(not(test))]
-fn run(m: ArgMatches, interface: Interface) {
+fn run_command<'c>(m: ArgMatches, interface: Interface, encoding: core::Encoding,
+                   cache: &'c core::FileCache<'c>) {
     use CompletePrinter::{Normal, WithSnippets};
     // match raw subcommand, and get it's sub-matches "m"
     if let (name, Some(sub_m)) = m.subcommand() {
         let mut cfg = Config::from(sub_m);
         cfg.interface = interface;
+        cfg.encoding = encoding;
+        cfg.fuzzy = sub_m.is_present("fuzzy");
+        cfg.docs = sub_m.is_present("docs");
+        // --substitute and --encoding can be given either side of the subcommand
+        for matches in &[&m, sub_m] {
+            if let Some(values) = matches.values_of("substitute") {
+                cfg.substitutes.extend(parse_substitutes(values));
+            }
+            match matches.value_of("encoding") {
+                Some("bytes") => cfg.encoding = core::Encoding::Bytes,
+                Some("chars") => cfg.encoding = core::Encoding::Chars,
+                Some("utf16") => cfg.encoding = core::Encoding::Utf16,
+                _ => {}
+            }
+        }
         match name {
-            "daemon"                => daemon(cfg),
-            "prefix"                => prefix(cfg),
-            "complete"              => complete(cfg, Normal),
-            "complete-with-snippet" => complete(cfg, WithSnippets),
-            "find-definition"       => find_definition(cfg),
+            "daemon"                => daemon(cfg, cache),
+            "lsp"                   => lsp(),
+            "prefix"                => prefix(cfg, cache),
+            "complete"              => complete(cfg, Normal, cache),
+            "complete-with-snippet" => complete(cfg, WithSnippets, cache),
+            "find-definition"       => find_definition(cfg, cache),
+            "type-at"               => type_at(cfg, cache),
+            "doc"                   => doc(cfg, cache),
+            "fill-match"            => fill_match(cfg, cache),
+            "find-references"       => find_references(cfg, cache),
+            "signature-help"        => signature_help(cfg, cache),
+            "outline"               => outline(sub_m, cfg, cache),
+            "workspace-symbols"     => workspace_symbols(sub_m, cfg, cache),
+            "rename"                => rename(sub_m, cfg, cache),
+            "open-buffer"           => cache_buffer(sub_m, interface, cache),
+            "replace-buffer"        => cache_buffer(sub_m, interface, cache),
+            "edit-buffer"           => edit_buffer(sub_m, interface, cfg.encoding, cache),
+            "close-buffer"          => close_buffer(sub_m, interface, cache),
             _                       => unreachable!()
         }
     }
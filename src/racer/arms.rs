@@ -0,0 +1,278 @@
+// Match arms
+//
+// The type of a match's scrutinee is inferred with ast::get_type_of. When it is an enum, its
+// variants are offered as completions in the arms' patterns, and the arms for the variants that
+// no pattern covers yet can be generated.
+
+use ast;
+use core::{self, Match, MatchType, SearchType, Session, Ty};
+use nameres;
+use rename::Edit;
+use scopes;
+use typeinf;
+use util::is_ident_char;
+
+use std::path::Path;
+
+#[derive(Debug,Clone)]
+pub struct MissingArms {
+    pub enummatch: Match,
+    pub arms: Vec<String>,     // e.g. `Shape::Square(_) => unimplemented!(),`
+    pub edit: Edit,            // adds all the arms, each on its own line, after the last arm
+}
+
+/// If start is where an arm's pattern goes and the scrutinee is an enum, its variants matching
+/// searchstr. None otherwise, or if no variant matches.
+pub fn complete_variants(src: &str, filepath: &Path, start: usize, searchstr: &str,
+                         search_type: SearchType, session: &Session) -> Option<Vec<Match>> {
+    let indexed = core::new_source(src.to_owned());
+    let msrc = scopes::mask_comments(indexed.as_ref());
+    scopes::find_match_arm_pattern(&msrc, start)
+        .and_then(|(keyword, brace)| scrutinee_enum(&msrc, keyword, brace, filepath, session))
+        .map(|enummatch| nameres::search_enum_variants(&enummatch, searchstr, search_type, session)
+             .collect::<Vec<_>>())
+        .and_then(|variants| if variants.is_empty() { None } else { Some(variants) })
+}
+
+/// The arms that the match at pos (anywhere in its arms, or on its `match` keyword) is missing,
+/// if its scrutinee is an enum.
+///
+/// A catch-all pattern (`_`, or a binding that isn't a const or variant) covers everything, so
+/// leaves no arms missing.
+pub fn fill_match(src: &str, filepath: &Path, pos: usize, session: &Session) -> Option<MissingArms> {
+    let indexed = core::new_source(src.to_owned());
+    let msrc = scopes::mask_comments(indexed.as_ref());
+    let (keyword, brace) = match scopes::find_enclosing_match(&msrc, pos) {
+        Some(found) => found,
+        None => return None
+    };
+    let enummatch = match scrutinee_enum(&msrc, keyword, brace, filepath, session) {
+        Some(m) => m,
+        None => return None
+    };
+    let body = scopes::end_of_next_scope(&msrc[brace..]);
+    if body.is_empty() {
+        // not closed yet
+        return None;
+    }
+    let close = brace + body.len() - 1;
+
+    let patterns = arm_patterns(&msrc[brace + 1..close]);
+    let covered: Vec<&str> = patterns.iter().filter_map(|pattern| variant_name(pattern)).collect();
+    let catch_all = patterns.iter().filter_map(|pattern| bare_binding(pattern))
+        .any(|name| name == "_" || !names_a_constant(name, filepath, keyword, session));
+    let variants = nameres::search_enum_variants(&enummatch, "", SearchType::StartsWith, session);
+    let arms: Vec<String> = if catch_all {
+        Vec::new()
+    } else {
+        variants.filter(|v| !covered.contains(&&v.matchstr[..]))
+            .map(|v| format!("{} => unimplemented!(),",
+                             variant_pattern(&v, &enummatch, filepath, keyword, session)))
+            .collect()
+    };
+
+    // the arms go after the last arm, indented like the first one
+    let insert = brace + 1 + msrc[brace + 1..close].trim_right().len();
+    let indent = match msrc[brace + 1..close].find(|c: char| !c.is_whitespace()) {
+        Some(i) if msrc[brace + 1..brace + 1 + i].contains('\n') => line_indent(&msrc, brace + 1 + i),
+        _ => line_indent(&msrc, keyword) + "    "
+    };
+    let last = msrc[..insert].trim_right();
+    let needs_comma = !(last.ends_with(',') || last.ends_with('{') || last.ends_with('}'));
+    let mut replacement = if needs_comma && insert > brace + 1 { ",".to_owned() } else { String::new() };
+    for arm in &arms {
+        replacement.push('\n');
+        replacement.push_str(&indent);
+        replacement.push_str(arm);
+    }
+    let mut end = insert;
+    if !msrc[insert..close].contains('\n') && !arms.is_empty() {
+        // the closing brace was on the same line, move it onto its own
+        replacement.push('\n');
+        replacement.push_str(&line_indent(&msrc, keyword));
+        end = close;
+    }
+
+    Some(MissingArms {
+        enummatch: enummatch,
+        arms: arms,
+        edit: Edit {
+            filepath: filepath.to_path_buf(),
+            start: insert,
+            end: end,
+            replacement: replacement,
+        },
+    })
+}
+
+/// The enum that the scrutinee of the match at keyword is, looking through references
+fn scrutinee_enum(msrc: &str, keyword: usize, brace: usize, filepath: &Path,
+                  session: &Session) -> Option<Match> {
+    let expr = msrc[keyword + "match".len()..brace].trim();
+    debug!("scrutinee_enum: |{}|", expr);
+    let mut ty = ast::get_type_of(expr.to_owned(), filepath, keyword, session);
+    while let Some(Ty::TyRefPtr(inner)) = ty {
+        ty = Some(*inner);
+    }
+    match ty {
+        Some(Ty::TyMatch(m)) => if m.mtype == MatchType::Enum { Some(m) } else { None },
+        _ => None
+    }
+}
+
+// Each alternative of the arms' patterns without any `x @` binding, e.g. `Shape::Circle { .. }`
+// and `Shape::Square(_)` for `Shape::Circle { .. } | s @ Shape::Square(_) if x > 1 =>`
+fn arm_patterns(arms: &str) -> Vec<String> {
+    let bytes = arms.as_bytes();
+    let mut out = Vec::new();
+    let mut depth = 0;
+    // where the current pattern starts: after the last comma or block that ended an arm
+    let mut starts = vec![0];
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    starts.push(i + 1);
+                }
+            }
+            b',' if depth == 0 => starts.push(i + 1),
+            b'>' if depth == 0 && i > 0 && bytes[i - 1] == b'=' => {
+                // struct patterns end in a brace too, so the pattern starts after the last
+                // separator that is followed by something that can start a pattern
+                let pattern = starts.iter().rev()
+                    .map(|&start| arms[start..i - 1].trim())
+                    .find(|pattern| !(pattern.is_empty() || pattern.starts_with('|') ||
+                                      pattern.starts_with("if ") || pattern.starts_with("else")))
+                    .unwrap_or("");
+                let pattern = match pattern.find(" if ") {
+                    Some(guard) => &pattern[..guard],
+                    None => pattern
+                };
+                for alt in split_alternatives(pattern) {
+                    out.push(alt.rsplit('@').next().unwrap().trim().to_owned());
+                }
+                starts = vec![i + 1];
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+// the `|` separated alternatives of a pattern
+fn split_alternatives(pattern: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, b) in pattern.bytes().enumerate() {
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'|' if depth == 0 => {
+                out.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&pattern[start..]);
+    out
+}
+
+// The last path segment of a pattern, e.g. `Circle` for `Shape::Circle { .. }`
+fn variant_name(pattern: &str) -> Option<&str> {
+    let pathend = pattern.find(|c: char| !(is_ident_char(c) || c == ':')).unwrap_or(pattern.len());
+    let name = pattern[..pathend].rsplit("::").next().unwrap();
+    if name.is_empty() { None } else { Some(name) }
+}
+
+// The name bound by a pattern that is just a lowercase identifier, e.g. `other` or
+// `ref mut other`, or `_`. These match anything, unless the name is a const or variant.
+fn bare_binding(pattern: &str) -> Option<&str> {
+    let mut name = pattern;
+    for prefix in &["ref ", "mut "] {
+        if name.starts_with(prefix) {
+            name = name[prefix.len()..].trim_left();
+        }
+    }
+    let is_ident = !name.is_empty() && name.chars().all(|c| is_ident_char(c) && c != '!');
+    if is_ident && name.starts_with(|c: char| c.is_lowercase() || c == '_') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn names_a_constant(name: &str, filepath: &Path, pos: usize, session: &Session) -> bool {
+    nameres::resolve_path_with_str(&core::Path::from_vec(false, vec![name]), filepath, pos,
+                                   SearchType::ExactMatch, core::Namespace::ValueNamespace, session)
+        .any(|m| match m.mtype {
+            MatchType::Const | MatchType::Static | MatchType::EnumVariant => true,
+            _ => false
+        })
+}
+
+/// The pattern for a variant, with a `_` for each of its fields. The variant is qualified with
+/// the enum's name unless it can be named on its own at the match (e.g. `Some`).
+fn variant_pattern(variant: &Match, enummatch: &Match, filepath: &Path, pos: usize,
+                   session: &Session) -> String {
+    let in_scope = nameres::resolve_path_with_str(&core::Path::from_vec(false, vec![&variant.matchstr[..]]),
+                                                  filepath, pos, SearchType::ExactMatch,
+                                                  core::Namespace::BothNamespaces, session)
+        .any(|m| m.point == variant.point && m.filepath == variant.filepath);
+    let mut out = if in_scope {
+        variant.matchstr.clone()
+    } else {
+        format!("{}::{}", enummatch.matchstr, variant.matchstr)
+    };
+
+    let src = session.load_file(&variant.filepath);
+    let fields = src[variant.point + variant.matchstr.len()..variant.item_range.1].trim_left();
+    if fields.starts_with('(') {
+        let n = typeinf::get_tuplestruct_fields(variant, session).len();
+        out.push('(');
+        out.push_str(&vec!["_"; n].join(", "));
+        out.push(')');
+    } else if fields.starts_with('{') {
+        let names: Vec<String> = typeinf::get_struct_fields(variant, session).into_iter()
+            .map(|(name, _, _)| name + ": _")
+            .collect();
+        out.push_str(" { ");
+        out.push_str(&names.join(", "));
+        out.push_str(" }");
+    }
+    out
+}
+
+// the whitespace at the start of the line containing point
+fn line_indent(src: &str, point: usize) -> String {
+    let linestart = src[..point].rfind('\n').map_or(0, |i| i + 1);
+    src[linestart..].chars().take_while(|&c| c == ' ' || c == '\t').collect()
+}
+
+#[test]
+fn finds_the_variants_that_arms_cover() {
+    let arms = "
+        Shape::Circle { radius, .. } if radius > 1.0 => { 1 }
+        Square(_) | Shape::Triangle(..) => if a { 2 } else { 3 }
+        Shape::Point { .. } | Shape::Line { .. } => 2,
+        s @ Shape::Empty => 3,
+        ";
+    let covered: Vec<_> = arm_patterns(arms).iter().filter_map(|p| variant_name(p).map(str::to_owned)).collect();
+    assert_eq!(vec!["Circle", "Square", "Triangle", "Point", "Line", "Empty"], covered);
+    assert_eq!(vec!["_"], arm_patterns("_ => {}"));
+}
+
+#[test]
+fn only_bare_names_are_bindings() {
+    assert_eq!(Some("other"), bare_binding("other"));
+    assert_eq!(Some("other"), bare_binding("ref mut other"));
+    assert_eq!(Some("_"), bare_binding("_"));
+    assert_eq!(None, bare_binding("Other"));
+    assert_eq!(None, bare_binding("consts::limit"));
+    assert_eq!(None, bare_binding("foo(_)"));
+    assert_eq!(None, bare_binding("foo { .. }"));
+}
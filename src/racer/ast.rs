@@ -1,6 +1,9 @@
 use core::{self, Match, MatchType, Scope, Ty, Session};
 use typeinf;
+use scopes;
 use nameres::{self, resolve_path_with_str};
+use util::{find_ident_occurrences, is_ident_char};
+use codecleaner::code_chunks;
 use core::Ty::*;
 
 use std::path::Path;
@@ -29,13 +32,57 @@ pub fn with_error_checking_parse<F, T>(s: String, f: F) -> Option<T> where F: Fn
     let sh = Handler::new(ColorConfig::Never, None, false, false, cm.clone());
     let ps = ParseSess::with_span_handler(sh, cm);
 
-    let mut p = match string_to_parser(&ps, s) {
+    let mut p = match string_to_parser(&ps, mask_trait_object_syntax(&s)) {
         Some(p) => p,
         None => return None
     };
     f(&mut p)
 }
 
+// The parser predates `dyn Trait` and `impl Trait` types. Where they are in a type position,
+// `dyn` is blanked out, leaving a bare trait object, and `impl ` becomes `for<>`, which parses
+// as a trait object too. Neither moves anything, so points into the source still line up.
+//
+// Only code is looked at: comments and the contents of strings are blanked out first, so neither
+// the keywords in them nor the text before an item (e.g. a comment ending in `:`) count.
+fn mask_trait_object_syntax(src: &str) -> String {
+    let mut code = vec![b' '; src.len()];
+    for (start, end) in code_chunks(src) {
+        code[start..end].copy_from_slice(&src.as_bytes()[start..end]);
+    }
+    // the blanks replace whole comments and strings, so it's still utf8
+    let code = String::from_utf8(code).unwrap();
+
+    let mut out = src.as_bytes().to_vec();
+    for &(keyword, replacement) in &[("dyn", "   "), ("impl", "for<>")] {
+        for start in find_ident_occurrences(&code, keyword) {
+            let end = start + keyword.len();
+            let followed_by_type = code[end..].starts_with(|c| c == ' ' || c == '\t' || c == '\n') &&
+                code[end..].trim_left().starts_with(|c: char| is_ident_char(c) || c == ':');
+            let before = code[..start].trim_right();
+            let in_type_position = before.ends_with(|c| "<(,&:=>[".contains(c)) ||
+                (before.ends_with("mut") && !before[..before.len() - 3].ends_with(is_ident_char));
+            if followed_by_type && in_type_position {
+                for (i, b) in replacement.bytes().enumerate() {
+                    out[start + i] = b;
+                }
+            }
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn masks_dyn_and_impl_trait_in_type_positions() {
+    assert_eq!("fn f(w: &mut     Write) -> for<>Iterator<Item = u8> {}",
+               mask_trait_object_syntax("fn f(w: &mut dyn Write) -> impl Iterator<Item = u8> {}"));
+    let src = "impl Foo for Bar {} let dyn = 1; foo(dyn, x)";
+    assert_eq!(src, mask_trait_object_syntax(src));
+    // nor in strings and comments, or after them
+    let src = "let s = (\"impl Foo\", \"x: dyn Bar\"); // is:\nimpl Foo {} /* (dyn X) */";
+    assert_eq!(src, mask_trait_object_syntax(src));
+}
+
 // parse a string, return a stmt
 pub fn string_to_stmt(source_str: String) -> Option<P<ast::Stmt>> {
     with_error_checking_parse(source_str, |p| {
@@ -173,8 +220,35 @@ fn to_racer_ty(ty: &ast::Ty, scope: &Scope) -> Option<Ty> {
         ast::TyRptr(ref _lifetime, ref ty) => {
             to_racer_ty(&*ty.ty, scope).map(|ref_ty| TyRefPtr(Box::new(ref_ty)) )
         }
-        ast::TyPath(_, ref path) => {
-            Some(TyPathSearch(to_racer_path(path), scope.clone()))
+        ast::TyPath(Some(ref qself), ref path) => {
+            // `<T as Trait>::Output` is looked up as `T::Output`
+            if let ast::TyPath(None, ref selfpath) = qself.ty.node {
+                let mut racerpath = to_racer_path(selfpath);
+                racerpath.segments.extend(to_racer_path(path).segments.into_iter().skip(qself.position));
+                Some(TyPathSearch(racerpath, scope.clone()))
+            } else {
+                None
+            }
+        }
+        ast::TyPath(None, ref path) => {
+            // only traits have associated types to bind
+            if assoc_type_bindings(path).is_empty() {
+                Some(TyPathSearch(to_racer_path(path), scope.clone()))
+            } else {
+                Some(trait_object_ty(&[path], scope))
+            }
+        }
+        ast::TyObjectSum(ref ty, ref bounds) => {
+            if let ast::TyPath(_, ref path) = ty.node {
+                let mut paths = vec![path];
+                paths.extend(trait_refs_of_bounds(bounds));
+                Some(trait_object_ty(&paths, scope))
+            } else {
+                None
+            }
+        }
+        ast::TyPolyTraitRef(ref bounds) => {
+            Some(trait_object_ty(&trait_refs_of_bounds(bounds), scope))
         }
         ast::TyFixedLengthVec(ref ty, ref expr) => {
             to_racer_ty(ty, scope).map(|racer_ty| {
@@ -191,6 +265,29 @@ fn to_racer_ty(ty: &ast::Ty, scope: &Scope) -> Option<Ty> {
     }
 }
 
+fn trait_object_ty(paths: &[&ast::Path], scope: &Scope) -> Ty {
+    TyTraitObject(paths.iter().map(|path| to_racer_path(path)).collect(),
+                  paths.iter().flat_map(|path| assoc_type_bindings(path)).collect(),
+                  scope.clone())
+}
+
+// e.g. `Item` and `u8` for `Iterator<Item = u8>`
+fn assoc_type_bindings(path: &ast::Path) -> Vec<(String, core::Path)> {
+    path.segments.last().map_or(Vec::new(), |seg| {
+        seg.parameters.bindings().into_iter().filter_map(|binding| {
+            let ty = match binding.ty.node {
+                // HACK for now, treat refs the same as unboxed types
+                ast::TyRptr(_, ref mt) => &mt.ty,
+                _ => &binding.ty
+            };
+            match ty.node {
+                ast::TyPath(_, ref path) => Some((binding.ident.name.to_string(), to_racer_path(path))),
+                _ => None
+            }
+        }).collect()
+    })
+}
+
 fn point_is_in_span(point: u32, span: &codemap::Span) -> bool {
     let codemap::BytePos(lo) = span.lo;
     let codemap::BytePos(hi) = span.hi;
@@ -383,12 +480,27 @@ fn path_to_match(ty: Ty, session: &Session) -> Option<Ty> {
 fn find_type_match(path: &core::Path, fpath: &Path, pos: usize, session: &Session) -> Option<Ty> {
     debug!("find_type_match {:?}", path);
     let res = resolve_path_with_str(path, fpath, pos, core::SearchType::ExactMatch,
-               core::Namespace::TypeNamespace, session).nth(0).and_then(|m| {
+               core::Namespace::TypeNamespace, session).nth(0);
+    if res.as_ref().map_or(false, |m| m.mtype == MatchType::Trait) {
+        // a bare trait is a trait object
+        let scope = Scope { filepath: fpath.to_path_buf(), point: pos };
+        return Some(TyTraitObject(vec![path.clone()], Vec::new(), scope));
+    }
+    let res = res.and_then(|m| {
                    match m.mtype {
                        MatchType::Type => get_type_of_typedef(m, session),
                        _ => Some(m)
                    }
+               }).or_else(|| {
+                   // not an item, maybe a generic parameter
+                   if path.global || path.segments.len() != 1 {
+                       return None;
+                   }
+                   typeinf::find_type_parameter(&path.segments[0].name, fpath, pos, session)
                });
+    if res.is_none() && path.segments.len() > 1 {
+        return find_assoc_type(path, fpath, pos, session);
+    }
 
     res.and_then(|m| {
         // add generic types to match (if any)
@@ -408,6 +520,43 @@ fn find_type_match(path: &core::Path, fpath: &Path, pos: usize, session: &Sessio
     })
 }
 
+// An associated type, e.g. `Self::Item` or `Counter::Item`, from the `type Item = ...;` in an
+// impl of the type or from the bindings of a trait object
+fn find_assoc_type(path: &core::Path, fpath: &Path, pos: usize, session: &Session) -> Option<Ty> {
+    let name = &path.segments[path.segments.len() - 1].name;
+    let mut selfpath = path.clone();
+    selfpath.segments.pop();
+
+    let selfty = if !selfpath.global && selfpath.segments.len() == 1 && selfpath.segments[0].name == "Self" {
+        let msrc = session.load_file_and_mask_comments(fpath);
+        let implstart = scopes::find_impl_start(msrc, pos, 0);
+        if let Some(start) = implstart {
+            // in an impl, the impl's own
+            if msrc[start..].starts_with("impl") {
+                if let Some(typesearch) = typeinf::get_assoc_type_in_impl(msrc, start, fpath, name) {
+                    return find_type_match(&typesearch.path, &typesearch.filepath, typesearch.point, session);
+                }
+            }
+        }
+        implstart.and_then(|_| typeinf::get_type_of_self(pos, fpath, true, msrc, session))
+    } else {
+        find_type_match(&selfpath, fpath, pos, session)
+    };
+
+    let typesearch = match selfty {
+        Some(TyMatch(ref m)) if m.mtype == MatchType::Struct || m.mtype == MatchType::Enum =>
+            typeinf::get_assoc_type_of_impls(m, name, session),
+        Some(TyTraitObject(_, ref bindings, ref scope)) =>
+            bindings.iter().find(|&&(ref binding, _)| binding == name).map(|&(_, ref path)| {
+                core::PathSearch { path: path.clone(), filepath: scope.filepath.clone(), point: scope.point }
+            }),
+        _ => None
+    };
+    typesearch.and_then(|typesearch| {
+        find_type_match(&typesearch.path, &typesearch.filepath, typesearch.point, session)
+    })
+}
+
 fn get_type_of_typedef(m: Match, session: &Session) -> Option<Match> {
     debug!("get_type_of_typedef match is {:?}", m);
     let msrc = session.load_file_and_mask_comments(&m.filepath);
@@ -493,25 +642,36 @@ impl<'c, 's, 'v> visit::Visitor<'v> for ExprTypeVisitor<'c, 's> {
                 //println!("obj expr is {:?}",objexpr);
                 self.visit_expr(objexpr);
 
-                self.result = self.result.as_ref().and_then(|contextm| {
-                    match *contextm {
-                        TyMatch(ref contextm) => {
-                            let omethod = nameres::search_for_impl_methods(
+                let contexts = match self.result {
+                    Some(TyMatch(ref contextm)) => vec![contextm.clone()],
+                    Some(TyTraitObject(ref paths, ref bindings, ref scope)) =>
+                        typeinf::get_traits_of_trait_object(paths, bindings, scope, self.session),
+                    Some(TyVec(_)) | Some(TyFixedLengthVec(..)) =>
+                        nameres::resolve_primitive("[T]", &self.scope.filepath, self.session)
+                        .into_iter().collect(),
+                    _ => Vec::new()
+                };
+                let session = self.session;
+                self.result = contexts.iter().filter_map(|contextm| {
+                    let omethod = match contextm.mtype {
+                        // the methods of traits, and of the traits bounding type parameters
+                        MatchType::Trait | MatchType::TypeParameter =>
+                            nameres::search_for_field_or_method(contextm.clone(), &methodname,
+                                                                core::SearchType::ExactMatch, session)
+                            .find(|m| m.mtype == MatchType::Function),
+                        _ => nameres::search_for_impl_methods(
                                 &contextm,
                                 &methodname,
                                 contextm.point,
                                 &contextm.filepath,
                                 contextm.local,
                                 core::SearchType::ExactMatch,
-                                self.session).nth(0);
-                            omethod
-                                .and_then(|method|
-                                          typeinf::get_return_type_of_function(&method, self.session))
-                                .and_then(|ty| path_to_match_including_generics(ty, contextm, self.session))
-                        }
-                        _ => None
-                    }
-                });
+                                session).nth(0)
+                    };
+                    omethod
+                        .and_then(|method| typeinf::get_return_type_of_function(&method, session))
+                        .and_then(|ty| path_to_match_including_generics(ty, contextm, session))
+                }).nth(0);
             }
 
             ast::ExprField(ref subexpression, spannedident) => {
@@ -534,6 +694,29 @@ impl<'c, 's, 'v> visit::Visitor<'v> for ExprTypeVisitor<'c, 's> {
                                 });
             }
 
+            ast::ExprTupField(ref subexpression, ref spannedindex) => {
+                let index = spannedindex.node;
+                debug!("exprtupfield {}", index);
+                self.visit_expr(subexpression);
+                let session = self.session;
+                self.result = self.result.take().and_then(|ty| path_to_match(ty, session))
+                    .and_then(|ty| match ty {
+                        TyTuple(elems) => elems.into_iter().nth(index)
+                            .and_then(|elem| path_to_match(elem, session)),
+                        TyMatch(ref structm) =>
+                            typeinf::get_tuplestruct_field_type(index as u32, structm, session)
+                            .and_then(|fieldty| path_to_match_including_generics(fieldty, structm, session)),
+                        _ => None
+                    });
+            }
+
+            ast::ExprIndex(ref subexpression, ref index) => {
+                let range = if let ast::ExprRange(..) = index.node { true } else { false };
+                self.visit_expr(subexpression);
+                self.result = self.result.take()
+                    .and_then(|ty| indexed_ty(ty, range, self.session));
+            }
+
             ast::ExprTup(ref exprs) => {
                 let mut v = Vec::new();
                 for expr in exprs {
@@ -549,8 +732,26 @@ impl<'c, 's, 'v> visit::Visitor<'v> for ExprTypeVisitor<'c, 's> {
                 self.result = Some(TyTuple(v));
             }
 
-            ast::ExprLit(_) => {
-                self.result = Some(TyUnsupported);
+            ast::ExprLit(ref lit) => {
+                let primitive = match lit.node {
+                    ast::LitStr(..) => Some("str"),
+                    ast::LitChar(_) => Some("char"),
+                    ast::LitByte(_) => Some("u8"),
+                    ast::LitBool(_) => Some("bool"),
+                    ast::LitInt(_, ast::SignedIntLit(ty, _)) => Some(ty.ty_to_string()),
+                    ast::LitInt(_, ast::UnsignedIntLit(ty)) => Some(ty.ty_to_string()),
+                    ast::LitFloat(_, ty) => Some(ty.ty_to_string()),
+                    // the types that unsuffixed literals default to
+                    ast::LitInt(_, ast::UnsuffixedIntLit(_)) => Some("i32"),
+                    ast::LitFloatUnsuffixed(_) => Some("f64"),
+                    ast::LitByteStr(_) => None
+                };
+                let is_str = if let ast::LitStr(..) = lit.node { true } else { false };
+                self.result = primitive
+                    .and_then(|name| nameres::resolve_primitive(name, &self.scope.filepath,
+                                                                self.session))
+                    .map(|m| if is_str { TyRefPtr(Box::new(TyMatch(m))) } else { TyMatch(m) })
+                    .or(Some(TyUnsupported));
             }
 
             _ => {
@@ -560,33 +761,86 @@ impl<'c, 's, 'v> visit::Visitor<'v> for ExprTypeVisitor<'c, 's> {
     }
 }
 
+// The type of indexing a value of type ty, through the element type of slices and arrays or the
+// Output of the Index impls of other types
+fn indexed_ty(ty: Ty, range: bool, session: &Session) -> Option<Ty> {
+    match ty {
+        TyRefPtr(ty) => indexed_ty(*ty, range, session),
+        TyVec(elem) | TyFixedLengthVec(elem, _) => {
+            if range {
+                Some(TyVec(elem))
+            } else {
+                path_to_match(*elem, session)
+            }
+        }
+        TyMatch(ref m) => {
+            let output = |range| typeinf::get_index_output_of_impls(m, range, session).and_then(|output| {
+                let scope = Scope { filepath: output.filepath, point: output.point };
+                path_to_match_including_generics(TyPathSearch(output.path, scope), m, session)
+            });
+            if range {
+                // e.g. the `[T]` of a Vec<T>, which isn't a path
+                output(true).or_else(|| output(false).map(|elem| TyVec(Box::new(elem))))
+            } else {
+                output(false)
+            }
+        }
+        _ => None
+    }
+}
+
 // gets generics info from the context match
 fn path_to_match_including_generics(ty: Ty, contextm: &Match, session: &Session) -> Option<Ty> {
     match ty {
         TyPathSearch(ref fieldtypepath, ref scope) => {
+            if let Some(typesearch) = generic_type_in_context(fieldtypepath, contextm, session) {
+                // yes! a generic type match!
+                return find_type_match(&typesearch.path,
+                                       &typesearch.filepath,
+                                       typesearch.point,
+                                       session);
+            }
 
-            if fieldtypepath.segments.len() == 1 {
-                // could be a generic arg! - try and resolve it
-                let typename = &fieldtypepath.segments[0].name;
-                let it = contextm.generic_args.iter()
-                    .zip(contextm.generic_types.iter());
-                for (name, typesearch) in it {
-                    if name == typename {
-                        // yes! a generic type match!
-                        return find_type_match(&typesearch.path,
-                                               &typesearch.filepath,
-                                               typesearch.point,
-                                               session);
+            find_type_match(fieldtypepath, &scope.filepath, scope.point, session).map(|ty| {
+                match ty {
+                    // e.g. the Self::Item of Option<Self::Item>, which only the context knows
+                    TyMatch(mut m) => {
+                        for typesearch in m.generic_types.iter_mut() {
+                            if let Some(found) = generic_type_in_context(&typesearch.path, contextm, session) {
+                                *typesearch = found;
+                            }
+                        }
+                        TyMatch(m)
                     }
+                    ty => ty
                 }
-            }
-
-            find_type_match(fieldtypepath, &scope.filepath, scope.point, session)
+            })
         }
+        TyRefPtr(ty) => path_to_match_including_generics(*ty, contextm, session),
         _ => Some(ty)
     }
 }
 
+// The type that the context gives path: a generic arg of a struct (`T`), or an associated type
+// (`Self::Item`) that a trait object binds or that one of the type's impls defines
+fn generic_type_in_context(path: &core::Path, contextm: &Match,
+                           session: &Session) -> Option<core::PathSearch> {
+    let (typename, is_assoc) = match path.segments.len() {
+        1 => (&path.segments[0].name, false),
+        2 if path.segments[0].name == "Self" => (&path.segments[1].name, true),
+        _ => return None
+    };
+    contextm.generic_args.iter()
+        .zip(contextm.generic_types.iter())
+        .find(|&(name, _)| name == typename)
+        .map(|(_, typesearch)| typesearch.clone())
+        .or_else(|| match contextm.mtype {
+            MatchType::Struct | MatchType::Enum if is_assoc =>
+                typeinf::get_assoc_type_of_impls(contextm, typename, session),
+            _ => None
+        })
+}
+
 
 fn find_type_match_including_generics(fieldtype: &core::Ty,
                                       filepath: &Path,
@@ -688,17 +942,38 @@ impl<'v> visit::Visitor<'v> for TypeVisitor {
 }
 
 pub struct TraitVisitor {
-    pub name: Option<String>
+    pub name: Option<String>,
+    pub supertraits: Vec<String>
 }
 
 impl<'v> visit::Visitor<'v> for TraitVisitor {
     fn visit_item(&mut self, item: &ast::Item) {
-        if let ast::ItemTrait(_, _, _, _) = item.node {
+        if let ast::ItemTrait(_, _, ref bounds, _) = item.node {
             self.name = Some(item.ident.name.to_string());
+            self.supertraits = trait_bound_paths(bounds);
         }
     }
 }
 
+// The traits of the bounds, e.g. `io::Read` for `io::Read + 'a`. `?Sized` isn't a trait the type
+// implements, so is left out.
+fn trait_refs_of_bounds(bounds: &ast::TyParamBounds) -> Vec<&ast::Path> {
+    bounds.iter().filter_map(|bound| match *bound {
+        ast::TraitTyParamBound(ref poly, ast::TraitBoundModifier::None) => Some(&poly.trait_ref.path),
+        _ => None
+    }).collect()
+}
+
+// The trait bounds as path strings, without any generic args
+fn trait_bound_paths(bounds: &ast::TyParamBounds) -> Vec<String> {
+    trait_refs_of_bounds(bounds).into_iter().map(|path| {
+        let segments: Vec<String> = path.segments.iter()
+            .map(|seg| seg.identifier.name.to_string()).collect();
+        let prefix = if path.global { "::" } else { "" };
+        format!("{}{}", prefix, segments.join("::"))
+    }).collect()
+}
+
 #[derive(Debug)]
 pub struct ImplVisitor {
     pub name_path: Option<core::Path>,
@@ -719,6 +994,10 @@ impl<'v> visit::Visitor<'v> for ImplVisitor {
                         self.name_path = Some(to_racer_path(path));
                     }
                 }
+                ast::TyVec(_) => {
+                    // slices are named like nameres::resolve_primitive names them
+                    self.name_path = Some(core::Path::from_vec(false, vec!["[T]"]));
+                }
                 _ => {}
             }
             otrait.as_ref().map(|ref t| {
@@ -758,13 +1037,29 @@ impl<'v> visit::Visitor<'v> for ExternCrateVisitor {
 
 
 pub struct GenericsVisitor {
-    pub generic_args: Vec<String>
+    pub generic_args: Vec<String>,
+    // the trait bounds of each generic arg, from both the generics list and the where clause
+    pub generic_bounds: Vec<Vec<String>>
 }
 
 impl<'v> visit::Visitor<'v> for GenericsVisitor {
     fn visit_generics(&mut self, g: &ast::Generics) {
         for ty in g.ty_params.iter() {
             self.generic_args.push((&ty.ident.name).to_string());
+            self.generic_bounds.push(trait_bound_paths(&ty.bounds));
+        }
+        for predicate in &g.where_clause.predicates {
+            if let ast::WherePredicate::BoundPredicate(ref bp) = *predicate {
+                if let ast::TyPath(None, ref path) = bp.bounded_ty.node {
+                    if path.global || path.segments.len() != 1 {
+                        continue;
+                    }
+                    let name = path.segments[0].identifier.name.to_string();
+                    if let Some(i) = self.generic_args.iter().position(|arg| *arg == name) {
+                        self.generic_bounds[i].extend(trait_bound_paths(&bp.bounds));
+                    }
+                }
+            }
         }
     }
 }
@@ -846,7 +1141,7 @@ pub fn parse_impl(s: String) -> ImplVisitor {
 }
 
 pub fn parse_trait(s: String) -> TraitVisitor {
-    let mut v = TraitVisitor { name: None };
+    let mut v = TraitVisitor { name: None, supertraits: Vec::new() };
     if let Some(stmt) = string_to_stmt(s) {
         visit::walk_stmt(&mut v, &*stmt);
     }
@@ -862,7 +1157,7 @@ pub fn parse_struct_def(s: String) -> StructDefVisitor {
 }
 
 pub fn parse_generics(s: String) -> GenericsVisitor {
-    let mut v = GenericsVisitor { generic_args: Vec::new() };
+    let mut v = GenericsVisitor { generic_args: Vec::new(), generic_bounds: Vec::new() };
     if let Some(stmt) = string_to_stmt(s) {
         visit::walk_stmt(&mut v, &*stmt);
     }
@@ -1015,6 +1310,15 @@ impl<'c, 's, 'v> visit::Visitor<'v> for FnArgTypeVisitor<'c, 's> {
     }
 }
 
+#[test]
+fn collects_trait_bounds_of_generics_and_where_clauses() {
+    let g = parse_generics("fn foo<T: io::Read + Debug, U: ?Sized + 'static>(t: T) where U: ::Bar, T: Baz {}"
+                           .to_owned());
+    assert_eq!(vec!["T", "U"], g.generic_args);
+    assert_eq!(vec![vec!["io::Read", "Debug", "Baz"], vec!["::Bar"]], g.generic_bounds);
+    let t = parse_trait("trait Foo: Bar + std::fmt::Debug {}".to_owned());
+    assert_eq!(vec!["Bar", "std::fmt::Debug"], t.supertraits);
+}
 
 #[test]
 fn ast_sandbox() {
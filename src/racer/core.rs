@@ -1,14 +1,16 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
 use std::{vec, fmt};
 use std::path;
 use std::io;
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::slice;
 use std::cmp::{min, max};
 use std::iter::{Fuse, Iterator};
+use std::time::SystemTime;
 use codeiter::StmtIndicesIter;
 
 use typed_arena::Arena;
@@ -16,7 +18,13 @@ use typed_arena::Arena;
 use scopes;
 use nameres;
 use ast;
+use typeinf;
+use ranking;
+use docs;
+use arms;
+use impls::{self, ImplIndex};
 use codecleaner;
+use util::symbol_matches;
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 pub enum MatchType {
@@ -40,12 +48,14 @@ pub enum MatchType {
     Static,
     Macro,
     Builtin,
+    TypeParameter,
 }
 
 #[derive(Debug,Clone,Copy)]
 pub enum SearchType {
     ExactMatch,
-    StartsWith
+    StartsWith,
+    Fuzzy       // see util::fuzzy_score
 }
 
 #[derive(Debug,Clone,Copy)]
@@ -55,6 +65,18 @@ pub enum Namespace {
     BothNamespaces
 }
 
+// What the column of a line/column coordinate counts, see scopes::encoded_coords_to_point
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Encoding {
+    Bytes,
+    Chars,
+    Utf16       // code units, as used by LSP
+}
+
+impl Default for Encoding {
+    fn default() -> Self { Encoding::Bytes }
+}
+
 #[derive(Debug,Clone,Copy)]
 pub enum CompletionType {
     CompleteField,
@@ -66,11 +88,14 @@ pub struct Match {
     pub matchstr: String,
     pub filepath: path::PathBuf,
     pub point: usize,
+    pub name_range: (usize, usize),      // the identifier, starting at point
+    pub item_range: (usize, usize),      // the whole item, e.g. a fn including its body
     pub local: bool,
     pub mtype: MatchType,
     pub contextstr: String,
     pub generic_args: Vec<String>,
     pub generic_types: Vec<PathSearch>,  // generic types are evaluated lazily
+    pub docs: String,                    // filled in for definitions; see docs::fill_docs for completions
 }
 
 
@@ -80,11 +105,14 @@ impl Match {
             matchstr: self.matchstr.clone(),
             filepath: self.filepath.clone(),
             point: self.point,
+            name_range: self.name_range,
+            item_range: self.item_range,
             local: self.local,
             mtype: self.mtype,
             contextstr: self.contextstr.clone(),
             generic_args: self.generic_args.clone(),
             generic_types: generic_types,
+            docs: self.docs.clone(),
         }
     }
 }
@@ -132,14 +160,41 @@ pub enum Ty {
     TyFixedLengthVec(Box<Ty>, String), // ty, length expr as string
     TyRefPtr(Box<Ty>),
     TyVec(Box<Ty>),
+    // `dyn Trait`, `impl Trait` or a bare trait: the traits, the types bound to their
+    // associated types (e.g. `Item = u8`) and the scope to resolve both in
+    TyTraitObject(Vec<Path>, Vec<(String, Path)>, Scope),
     TyUnsupported
 }
 
+impl Ty {
+    /// The item that defines the type, looking through references and slices
+    pub fn defining_match(&self) -> Option<&Match> {
+        match *self {
+            Ty::TyMatch(ref m) => Some(m),
+            Ty::TyRefPtr(ref ty) |
+            Ty::TyVec(ref ty) |
+            Ty::TyFixedLengthVec(ref ty, _) => ty.defining_match(),
+            _ => None
+        }
+    }
+}
+
 impl fmt::Display for Ty {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Ty::TyMatch(ref m) => {
-                write!(f, "{}", m.matchstr)
+                try!(write!(f, "{}", m.matchstr));
+                if !m.generic_types.is_empty() {
+                    try!(write!(f, "<"));
+                    for (i, gen) in m.generic_types.iter().enumerate() {
+                        if i > 0 {
+                            try!(write!(f, ", "));
+                        }
+                        try!(write!(f, "{}", gen.path));
+                    }
+                    try!(write!(f, ">"));
+                }
+                Ok(())
             }
             Ty::TyPathSearch(ref p, _) => {
                 write!(f, "{}", p)
@@ -172,6 +227,21 @@ impl fmt::Display for Ty {
             Ty::TyRefPtr(ref ty) => {
                 write!(f, "&{}", ty)
             }
+            Ty::TyTraitObject(ref paths, ref bindings, _) => {
+                try!(write!(f, "dyn "));
+                for (i, path) in paths.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, " + "));
+                    }
+                    try!(write!(f, "{}", path));
+                    if i == 0 && !bindings.is_empty() {
+                        let bindings: Vec<String> = bindings.iter()
+                            .map(|&(ref name, ref ty)| format!("{} = {}", name, ty)).collect();
+                        try!(write!(f, "<{}>", bindings.join(", ")));
+                    }
+                }
+                Ok(())
+            }
             Ty::TyUnsupported => {
                 write!(f, "_")
             }
@@ -420,6 +490,10 @@ pub struct FileCache<'c> {
     allocations_available: RefCell<Vec<&'c mut IndexedSource>>,
     /// allocations that have been freed in the current generation.
     allocations_freed: RefCell<Vec<&'c IndexedSource>>,
+    /// modification time and length of files at the time they were read from disk (None if the
+    /// file didn't exist). Files cached with cache_file_contents have no entry since they don't
+    /// correspond to the disk contents.
+    disk_state: RefCell<HashMap<path::PathBuf, Option<(SystemTime, u64)>>>,
 }
 
 impl<'c> FileCache<'c> {
@@ -430,6 +504,7 @@ impl<'c> FileCache<'c> {
             masked_map: RefCell::new(HashMap::new()),
             allocations_available: RefCell::new(Vec::new()),
             allocations_freed: RefCell::new(Vec::new()),
+            disk_state: RefCell::new(HashMap::new()),
         }
     }
 
@@ -498,6 +573,9 @@ impl<'c> FileCache<'c> {
     /// Subsequent calls to load_file will return an IndexedSource of the provided buf.
     pub fn cache_file_contents<T>(&'c self, filepath: &path::Path, buf: T)
     where T: Into<String> {
+        // the cached contents take precedence over whatever is on disk
+        self.disk_state.borrow_mut().remove(filepath);
+
         // update raw file
         {
             let mut cache = self.raw_map.borrow_mut();
@@ -530,6 +608,76 @@ impl<'c> FileCache<'c> {
         }
     }
 
+    /// Replaces the text between the `start` and `end` byte offsets of the cached file with `text`.
+    ///
+    /// Files that aren't cached yet are read from disk first. Returns false (leaving the cache
+    /// untouched) if the range doesn't fall on character boundaries within the current contents.
+    pub fn edit_file_contents(&'c self, filepath: &path::Path,
+                              start: usize, end: usize, text: &str) -> bool {
+        let buf = {
+            let src = self.load_file(filepath);
+            let src = &*src;
+            if start > end || end > src.len() ||
+                !src.is_char_boundary(start) || !src.is_char_boundary(end) {
+                return false;
+            }
+            let mut buf = String::with_capacity(src.len() - (end - start) + text.len());
+            buf.push_str(&src[..start]);
+            buf.push_str(text);
+            buf.push_str(&src[end..]);
+            buf
+        };
+        self.cache_file_contents(filepath, buf);
+        true
+    }
+
+    /// Drops cached source for files that have changed on disk since they were loaded.
+    ///
+    /// This lets a long running process (e.g. the daemon) keep one FileCache across queries.
+    /// Contents provided through cache_file_contents are never invalidated here. Like
+    /// update_available_allocations, this should only be called while no Session is active.
+    pub fn invalidate_stale_files(&self) {
+        let stale = self.disk_state.borrow().iter()
+            .filter(|&(path, state)| disk_state_of(path) != *state)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+
+        for path in stale {
+            debug!("invalidating cached source for {:?}", path);
+            self.uncache_file(&path);
+        }
+    }
+
+    /// Rebuilds the cache's state after a query panicked, possibly part way through updating it.
+    ///
+    /// Files read from disk are forgotten, to be read again when needed. Contents provided
+    /// through cache_file_contents can't be read again so are kept, but their masked versions
+    /// are rebuilt. Like update_available_allocations, this should only be called while no
+    /// Session is active.
+    pub fn reset(&self) {
+        let ondisk = self.disk_state.borrow().keys().cloned().collect::<Vec<_>>();
+        for path in ondisk {
+            self.uncache_file(&path);
+        }
+        let mut freed = self.allocations_freed.borrow_mut();
+        for (_, prev) in self.masked_map.borrow_mut().drain() {
+            freed.push(prev);
+        }
+    }
+
+    /// Forgets any cached source for the path, e.g. when an editor closes an unsaved buffer.
+    ///
+    /// Subsequent calls to load_file will read the file from disk again.
+    pub fn uncache_file(&self, filepath: &path::Path) {
+        self.disk_state.borrow_mut().remove(filepath);
+        if let Some(prev) = self.raw_map.borrow_mut().remove(filepath) {
+            self.allocations_freed.borrow_mut().push(prev);
+        }
+        if let Some(prev) = self.masked_map.borrow_mut().remove(filepath) {
+            self.allocations_freed.borrow_mut().push(prev);
+        }
+    }
+
     pub fn open_file(&self, path: &path::Path) -> io::Result<File> {
         File::open(path)
     }
@@ -555,6 +703,7 @@ impl<'c> FileCache<'c> {
     pub fn load_file(&'c self, filepath: &path::Path) -> Src<'c> {
         let mut cache = self.raw_map.borrow_mut();
         cache.entry(filepath.to_path_buf()).or_insert_with(|| {
+            self.disk_state.borrow_mut().insert(filepath.to_path_buf(), disk_state_of(filepath));
             let rawbytes = self.read_file(filepath);
             let res = String::from_utf8(rawbytes).unwrap();
             self.alloc(IndexedSource::new(res))
@@ -571,44 +720,96 @@ impl<'c> FileCache<'c> {
     }
 }
 
+fn disk_state_of(filepath: &path::Path) -> Option<(SystemTime, u64)> {
+    fs::metadata(filepath).ok().and_then(|md| md.modified().ok().map(|mtime| (mtime, md.len())))
+}
+
+/// Where the contents of an overridden path are read from
+enum Substitute<'c> {
+    File(path::PathBuf),                          // e.g. an editor's temporary file
+    Contents(&'c IndexedSource, &'c IndexedSource) // raw and comment-masked unsaved contents
+}
+
 pub struct Session<'c> {
-    query_path: path::PathBuf,            // the input path of the query
-    substitute_file: path::PathBuf,       // the temporary file
-    cache: &'c FileCache<'c>              // cache for file contents
+    substitutes: HashMap<path::PathBuf, Substitute<'c>>, // unsaved versions of files
+    cache: &'c FileCache<'c>,                            // cache for file contents
+    impl_indexes: RefCell<HashMap<path::PathBuf, Rc<ImplIndex>>>, // by crate root
+    module_impl_indexes: RefCell<HashMap<(path::PathBuf, usize), Rc<ImplIndex>>>, // by module scope
+    prelude_traits: RefCell<HashMap<path::PathBuf, Rc<Vec<Match>>>>, // by std crate root
 }
 
 
 impl<'a> Drop for Session<'a> {
     fn drop(&mut self) {
+        // in-memory substitutes only live as long as the session
+        for (_, sub) in self.substitutes.drain() {
+            if let Substitute::Contents(raw, masked) = sub {
+                let mut freed = self.cache.allocations_freed.borrow_mut();
+                freed.push(raw);
+                freed.push(masked);
+            }
+        }
         unsafe { self.cache.update_available_allocations(); }
     }
 }
 
 impl<'c> fmt::Debug for Session<'c> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "Session({:?}, {:?})", self.query_path, self.substitute_file)
+        write!(f, "Session({:?})", self.substitutes.keys().collect::<Vec<_>>())
     }
 }
 
 impl<'c> Session<'c> {
+    /// Creates a session that reads every file through the cache
+    pub fn new(cache: &'c FileCache<'c>) -> Session<'c> {
+        Session {
+            substitutes: HashMap::new(),
+            cache: cache,
+            impl_indexes: RefCell::new(HashMap::new()),
+            module_impl_indexes: RefCell::new(HashMap::new()),
+            prelude_traits: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a session where the query path is read from substitute_file
     pub fn from_path(cache: &'c FileCache<'c>,
                      query_path: &path::Path,
                      substitute_file: &path::Path) -> Session<'c> {
-        Session {
-            query_path: query_path.to_path_buf(),
-            substitute_file: substitute_file.to_path_buf(),
-            cache: cache
+        let mut session = Session::new(cache);
+        if query_path != substitute_file {
+            session.substitute_file(query_path, substitute_file);
+        }
+        session
+    }
+
+    /// Reads `path` from `substitute_file` for the rest of the session
+    pub fn substitute_file(&mut self, path: &path::Path, substitute_file: &path::Path) {
+        self.substitutes.insert(path.to_path_buf(), Substitute::File(substitute_file.to_path_buf()));
+    }
+
+    /// Uses `contents` in place of the file at `path` for the rest of the session
+    ///
+    /// Unlike FileCache::cache_file_contents the contents are forgotten when the session ends.
+    pub fn substitute_contents<T>(&mut self, path: &path::Path, contents: T)
+    where T: Into<String> {
+        let cache = self.cache;
+        let raw: &'c IndexedSource = cache.alloc(IndexedSource::new(contents.into()));
+        let masked: &'c IndexedSource = cache.alloc(raw.with_src(scopes::mask_comments(raw.as_ref())));
+        if let Some(Substitute::Contents(prev_raw, prev_masked)) =
+                self.substitutes.insert(path.to_path_buf(), Substitute::Contents(raw, masked)) {
+            let mut freed = cache.allocations_freed.borrow_mut();
+            freed.push(prev_raw);
+            freed.push(prev_masked);
         }
     }
 
     /// Resolve appropriate path for current query
     ///
-    /// If path is the query path, returns the substitute file
+    /// If path has a substitute file, returns the substitute file
     fn resolve_path<'a>(&'a self, path: &'a path::Path) -> &path::Path {
-        if path == self.query_path.as_path() {
-            &self.substitute_file
-        } else {
-            path
+        match self.substitutes.get(path) {
+            Some(&Substitute::File(ref substitute_file)) => substitute_file,
+            _ => path
         }
     }
 
@@ -617,26 +818,87 @@ impl<'c> Session<'c> {
         self.cache.cache_file_contents(filepath, buf);
     }
 
+    /// Opens the file (or its substitute file) on disk, ignoring in-memory substitutes
     pub fn open_file(&self, path: &path::Path) -> io::Result<File> {
         self.cache.open_file(self.resolve_path(path))
     }
 
     pub fn read_file(&self, path: &path::Path) -> Vec<u8> {
-        self.cache.read_file(self.resolve_path(path))
+        match self.substitutes.get(path) {
+            Some(&Substitute::Contents(raw, _)) => raw.code.clone().into_bytes(),
+            _ => self.cache.read_file(self.resolve_path(path))
+        }
     }
 
     pub fn load_file(&self, filepath: &path::Path) -> Src<'c> {
-        self.cache.load_file(self.resolve_path(filepath))
+        match self.substitutes.get(filepath) {
+            Some(&Substitute::Contents(raw, _)) => raw.as_ref(),
+            _ => self.cache.load_file(self.resolve_path(filepath))
+        }
+    }
+
+    /// The index of the impls in the crate that filepath is in, built the first time it's needed
+    /// in the session
+    pub fn impl_index(&self, filepath: &path::Path) -> Rc<ImplIndex> {
+        let root = impls::crate_root(filepath);
+        if let Some(index) = self.impl_indexes.borrow().get(&root) {
+            return index.clone();
+        }
+        let index = Rc::new(impls::build_impl_index(&root, self));
+        self.impl_indexes.borrow_mut().insert(root, index.clone());
+        index
+    }
+
+    /// The index of the impls in the module whose items start at scopestart in filepath (see
+    /// impls::build_module_impl_index), built the first time it's needed in the session
+    pub fn module_impl_index(&self, filepath: &path::Path, scopestart: usize) -> Rc<ImplIndex> {
+        let key = (filepath.to_path_buf(), scopestart);
+        if let Some(index) = self.module_impl_indexes.borrow().get(&key) {
+            return index.clone();
+        }
+        let index = Rc::new(impls::build_module_impl_index(filepath, scopestart, self));
+        self.module_impl_indexes.borrow_mut().insert(key, index.clone());
+        index
+    }
+
+    /// The traits that the prelude of the std crate used by filepath brings into scope, e.g.
+    /// `ToString`, resolved the first time they're needed in the session
+    pub fn prelude_traits(&self, filepath: &path::Path) -> Rc<Vec<Match>> {
+        let stdlib = match nameres::get_crate_file("std", filepath) {
+            Some(stdlib) => stdlib,
+            None => return Rc::new(Vec::new())
+        };
+        if let Some(traits) = self.prelude_traits.borrow().get(&stdlib) {
+            return traits.clone();
+        }
+        let traits = Rc::new(nameres::search_prelude_traits(&stdlib, self));
+        self.prelude_traits.borrow_mut().insert(stdlib, traits.clone());
+        traits
     }
 
     pub fn load_file_and_mask_comments(&self, filepath: &path::Path) -> Src<'c> {
-        self.cache.load_file_and_mask_comments(self.resolve_path(filepath))
+        match self.substitutes.get(filepath) {
+            Some(&Substitute::Contents(_, masked)) => masked.as_ref(),
+            _ => self.cache.load_file_and_mask_comments(self.resolve_path(filepath))
+        }
     }
 }
 
-
+/// Completes the name ending at pos, best matches first (see ranking::rank_completions).
 pub fn complete_from_file(src: &str, filepath: &path::Path, 
                           pos: usize, session: &Session) -> vec::IntoIter<Match> {
+    complete_from_file_(src, filepath, pos, SearchType::StartsWith, session)
+}
+
+/// Like complete_from_file, but the name being completed can be a fuzzy abbreviation (e.g.
+/// `hmap` for `HashMap`, see util::fuzzy_score).
+pub fn fuzzy_complete_from_file(src: &str, filepath: &path::Path,
+                                pos: usize, session: &Session) -> vec::IntoIter<Match> {
+    complete_from_file_(src, filepath, pos, SearchType::Fuzzy, session)
+}
+
+fn complete_from_file_(src: &str, filepath: &path::Path, pos: usize,
+                       search_type: SearchType, session: &Session) -> vec::IntoIter<Match> {
     let start = scopes::get_start_of_search_expr(src, pos);
     let expr = &src[start..pos];
 
@@ -646,7 +908,18 @@ pub fn complete_from_file(src: &str, filepath: &path::Path,
 
     let mut out = Vec::new();
 
+    // struct literal fields and the variants in match arms are completed without a path
+    let pathless = match completetype {
+        CompletionType::CompletePath if contextstr.is_empty() =>
+            complete_struct_literal_fields(src, filepath, start, searchstr, search_type, session)
+            .or_else(|| arms::complete_variants(src, filepath, start, searchstr, search_type, session)),
+        _ => None
+    };
+
     match completetype {
+        CompletionType::CompletePath if pathless.is_some() => {
+            out.extend(pathless.unwrap());
+        },
         CompletionType::CompletePath => {
             let mut v = expr.split("::").collect::<Vec<_>>();
             let mut global = false;
@@ -657,7 +930,7 @@ pub fn complete_from_file(src: &str, filepath: &path::Path,
 
             let path = Path::from_vec(global, v);
             for m in nameres::resolve_path(&path, filepath, pos,
-                                         SearchType::StartsWith, Namespace::BothNamespaces,
+                                         search_type, Namespace::BothNamespaces,
                                          session) {
                 out.push(m);
             }
@@ -666,14 +939,78 @@ pub fn complete_from_file(src: &str, filepath: &path::Path,
             let context = ast::get_type_of(contextstr.to_owned(), filepath, pos, session);
             debug!("complete_from_file context is {:?}", context);
             context.map(|ty| {
-                complete_field_for_ty(ty, searchstr, SearchType::StartsWith, session, &mut out);
+                complete_field_for_ty(ty, searchstr, search_type, filepath, pos, session, &mut out);
             });
         }
     }
+
+    // no docs here: they'd mean scanning every match's file, see docs::fill_docs
+    let out: Vec<Match> = ranking::rank_completions(out, searchstr, filepath, session).into_iter()
+        .map(|m| fill_in_extent(m, session))
+        .collect();
     out.into_iter()
 }
 
-fn complete_field_for_ty(ty: Ty, searchstr: &str, stype: SearchType, session: &Session, out: &mut Vec<Match>) {
+/// If start is a field name in a struct literal, the fields of the struct that aren't given yet.
+///
+/// None if start isn't in a struct literal, or its path doesn't resolve to a struct or an enum
+/// struct variant.
+fn complete_struct_literal_fields(src: &str, filepath: &path::Path, start: usize, searchstr: &str,
+                                  search_type: SearchType, session: &Session) -> Option<Vec<Match>> {
+    let indexed = new_source(src.to_owned());
+    let msrc = scopes::mask_comments(indexed.as_ref());
+    scopes::find_struct_literal(&msrc, start).and_then(|(path, brace)| {
+        debug!("complete_struct_literal_fields: |{}| literal", path);
+        resolve_struct_literal(path, &msrc, filepath, brace, session).map(|structm| {
+            let given = scopes::struct_literal_fields(&msrc, brace);
+            nameres::search_struct_fields(searchstr, &structm, search_type, session)
+                .filter(|m| !given.iter().any(|&(point, name)| point != start && name == m.matchstr))
+                .collect()
+        })
+    })
+}
+
+/// If start..end is a field name being given a value in a struct literal, e.g. `a` in
+/// `Foo { a: 1 }`, the definition of the field
+fn find_struct_literal_field(src: &str, filepath: &path::Path, start: usize, end: usize,
+                             session: &Session) -> Option<Match> {
+    let indexed = new_source(src.to_owned());
+    let msrc = scopes::mask_comments(indexed.as_ref());
+    let after = msrc[end..].trim_left();
+    if !after.starts_with(':') || after.starts_with("::") {
+        // shorthand fields name a local too, which is the more useful definition
+        return None;
+    }
+    scopes::find_struct_literal(&msrc, start).and_then(|(path, brace)| {
+        resolve_struct_literal(path, &msrc, filepath, brace, session)
+    }).and_then(|structm| {
+        nameres::search_struct_fields(&src[start..end], &structm, SearchType::ExactMatch, session).nth(0)
+    })
+}
+
+/// The struct or enum struct variant named by the path of a struct literal
+fn resolve_struct_literal(path: &str, msrc: &str, filepath: &path::Path, pos: usize,
+                          session: &Session) -> Option<Match> {
+    if path == "Self" {
+        match typeinf::get_type_of_self(pos, filepath, true, new_source(msrc.to_owned()).as_ref(), session) {
+            Some(Ty::TyMatch(m)) if m.mtype == MatchType::Struct => Some(m),
+            _ => None
+        }
+    } else {
+        let mut v = path.split("::").collect::<Vec<_>>();
+        let global = v[0] == "";
+        if global {
+            v.remove(0);
+        }
+        nameres::resolve_path(&Path::from_vec(global, v), filepath, pos,
+                              SearchType::ExactMatch, Namespace::BothNamespaces, session)
+            .find(|m| m.mtype == MatchType::Struct || m.mtype == MatchType::EnumVariant)
+    }
+}
+
+// filepath and pos are where the completion is, which is where the fields of tuples point
+fn complete_field_for_ty(ty: Ty, searchstr: &str, stype: SearchType, filepath: &path::Path,
+                         pos: usize, session: &Session, out: &mut Vec<Match>) {
     // TODO would be nice if this and other methods could operate on a ref instead of requiring
     // ownership
     match ty {
@@ -683,14 +1020,75 @@ fn complete_field_for_ty(ty: Ty, searchstr: &str, stype: SearchType, session: &S
             }
         },
         Ty::TyRefPtr(m) => {
-            complete_field_for_ty(*m.to_owned(), searchstr, stype, session, out)
+            complete_field_for_ty(*m.to_owned(), searchstr, stype, filepath, pos, session, out)
+        }
+        Ty::TyTraitObject(paths, bindings, scope) => {
+            for m in typeinf::get_traits_of_trait_object(&paths, &bindings, &scope, session) {
+                out.extend(nameres::search_for_field_or_method(m, searchstr, stype, session));
+            }
+        }
+        Ty::TyTuple(elems) => {
+            for (i, elem) in elems.iter().enumerate() {
+                let name = i.to_string();
+                if symbol_matches(stype, searchstr, &name) {
+                    out.push(Match {
+                        matchstr: name,
+                        filepath: filepath.to_path_buf(),
+                        point: pos,
+                        name_range: (pos, pos),
+                        item_range: (pos, pos),
+                        local: true,
+                        mtype: MatchType::StructField,
+                        contextstr: elem.to_string(),
+                        generic_args: Vec::new(),
+                        generic_types: Vec::new(),
+                        docs: String::new()
+                    });
+                }
+            }
+        }
+        // arrays get the methods of slices
+        Ty::TyVec(_) | Ty::TyFixedLengthVec(..) => {
+            if let Some(m) = nameres::resolve_primitive("[T]", filepath, session) {
+                out.extend(nameres::search_for_field_or_method(m, searchstr, stype, session));
+            }
         }
         _ => return
     }
 }
 
 pub fn find_definition(src: &str, filepath: &path::Path, pos: usize, session: &Session) -> Option<Match> {
-    find_definition_(src, filepath, pos, session)
+    find_definition_(src, filepath, pos, session).map(|m| fill_in_match(m, session))
+}
+
+// The searches leave out the docs, and the extent of modules that are a whole file since that
+// would mean loading the file
+fn fill_in_match(m: Match, session: &Session) -> Match {
+    docs::fill_docs(fill_in_extent(m, session), session)
+}
+
+fn fill_in_extent(mut m: Match, session: &Session) -> Match {
+    if m.point == 0 && m.item_range == (0, 0) {
+        m.item_range = (0, session.load_file(&m.filepath).len());
+    }
+    m
+}
+
+/// Infers the type of the expression at pos, e.g. to show on hover.
+///
+/// If the expression itself can't be typed (e.g. it names a method) this falls back to the type
+/// of the item that find_definition resolves it to.
+pub fn find_type_at(src: &str, filepath: &path::Path, pos: usize, session: &Session) -> Option<Ty> {
+    let (start, end) = scopes::expand_search_expr(src, pos);
+    let expr = &src[start..end];
+    debug!("find_type_at for |{}|", expr);
+
+    ast::get_type_of(expr.to_owned(), filepath, pos, session).or_else(|| {
+        find_definition(src, filepath, pos, session).and_then(|m| {
+            let msrc = session.load_file_and_mask_comments(&m.filepath);
+            typeinf::get_type_of_match(m, msrc, session)
+        })
+    })
 }
 
 pub fn find_definition_(src: &str, filepath: &path::Path, pos: usize, session: &Session) -> Option<Match> {
@@ -701,7 +1099,14 @@ pub fn find_definition_(src: &str, filepath: &path::Path, pos: usize, session: &
 
     debug!("find_definition_ for |{:?}| |{:?}| {:?}", contextstr, searchstr, completetype);
 
+    let literal_field = match completetype {
+        CompletionType::CompletePath if contextstr.is_empty() =>
+            find_struct_literal_field(src, filepath, start, end, session),
+        _ => None
+    };
+
     match completetype {
+        CompletionType::CompletePath if literal_field.is_some() => literal_field,
         CompletionType::CompletePath => {
             let mut v = expr.split("::").collect::<Vec<_>>();
             let mut global = false;
@@ -0,0 +1,220 @@
+// Documentation comments
+//
+// The masked source that racer searches has its comments blanked out, so docs are read back out
+// of the unmasked file: the `///` and `/** */` comments and `#[doc = "..."]` attributes just
+// above an item, or the `//!` comments at the top of a module's file.
+
+use core::{Match, Session};
+
+// what can come before the name of a documented item on its line
+const ITEM_KEYWORDS: &'static [&'static str] = &[
+    "pub", "fn", "struct", "enum", "trait", "type", "mod", "const", "static", "unsafe",
+    "extern", "impl", "macro_rules!"];
+
+/// Fills in m.docs from m's file
+pub fn fill_docs(mut m: Match, session: &Session) -> Match {
+    let src = session.load_file(&m.filepath);
+    if m.point <= src.len() {
+        m.docs = find_docs(&src, m.point);
+    }
+    m
+}
+
+/// The docs of the item whose name is at point, one string with a line per doc line.
+///
+/// A point of 0 is taken to be a module file, whose docs are its leading `//!` comments.
+pub fn find_docs(src: &str, point: usize) -> String {
+    if point == 0 {
+        module_doc_lines(src).join("\n")
+    } else {
+        doc_lines(src, point).join("\n")
+    }
+}
+
+/// The doc lines attached to the item declared on the line containing point
+pub fn doc_lines(src: &str, point: usize) -> Vec<String> {
+    let linestart = src[..point].rfind('\n').map_or(0, |i| i + 1);
+    let is_item = src[linestart..point].split_whitespace()
+        .all(|word| ITEM_KEYWORDS.contains(&word) || word.starts_with('"'));
+    if !is_item {
+        // e.g. a let binding or a function argument
+        return Vec::new();
+    }
+
+    // walking backwards, so each doc is collected in reverse and the whole lot flipped at the end
+    let mut out = Vec::new();
+    let mut lines = src[..linestart].lines().rev().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line.starts_with("///") && !line.starts_with("////") {
+            out.push(strip_line_comment(line));
+        } else if line.ends_with("*/") {
+            let mut block = vec![line];
+            let mut line = line;
+            while !line.contains("/*") {
+                line = match lines.next() {
+                    Some(line) => line,
+                    None => break
+                };
+                block.push(line);
+            }
+            if !line.contains("/*") {
+                break;
+            }
+            block.reverse();
+            let block = block.join("\n");
+            if block.starts_with("/**") && !block.starts_with("/***") {
+                out.extend(block_comment_lines(&block).into_iter().rev());
+            }
+        } else if line.ends_with(']') {
+            let mut attr = vec![line];
+            let mut line = line;
+            while !line.starts_with("#[") {
+                line = match lines.next() {
+                    Some(line) => line,
+                    None => break
+                };
+                attr.push(line);
+            }
+            if !line.starts_with("#[") {
+                break;
+            }
+            attr.reverse();
+            if let Some(doc) = doc_attribute(&attr.join(" ")) {
+                out.extend(doc.lines().rev().map(str::to_owned));
+            }
+        } else if line.is_empty() || line.starts_with("//") {
+            // blank lines and plain comments don't detach docs from the item
+            continue;
+        } else {
+            break;
+        }
+    }
+    out.reverse();
+    out
+}
+
+/// The `//!` and `/*! */` docs at the top of a module file
+fn module_doc_lines(src: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut lines = src.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line.starts_with("//!") {
+            out.push(strip_line_comment(line));
+        } else if line.starts_with("/*!") {
+            let mut block = vec![line];
+            let mut line = line;
+            while !line.contains("*/") {
+                line = match lines.next() {
+                    Some(line) => line,
+                    None => break
+                };
+                block.push(line);
+            }
+            out.extend(block_comment_lines(&block.join("\n")));
+        } else if line.starts_with("#![") {
+            if let Some(doc) = doc_attribute(line) {
+                out.extend(doc.lines().map(str::to_owned));
+            }
+        } else if line.is_empty() || line.starts_with("//") {
+            continue;
+        } else {
+            break;
+        }
+    }
+    out
+}
+
+// e.g. "/// foo" -> "foo", keeping any further indentation
+fn strip_line_comment(line: &str) -> String {
+    let doc = &line[3..];
+    if doc.starts_with(' ') { &doc[1..] } else { doc }.to_owned()
+}
+
+// the lines of a /** */ block, without the comment markers and leading ` * `
+fn block_comment_lines(block: &str) -> Vec<String> {
+    let inner = &block[3..];
+    let inner = if inner.ends_with("*/") { &inner[..inner.len() - 2] } else { inner };
+    let mut out: Vec<String> = inner.lines().map(|line| {
+        let line = line.trim();
+        let line = if line.starts_with('*') { &line[1..] } else { line };
+        if line.starts_with(' ') { &line[1..] } else { line }.trim_right().to_owned()
+    }).collect();
+    while out.last().map_or(false, |l| l.is_empty()) {
+        out.pop();
+    }
+    while out.first().map_or(false, |l| l.is_empty()) {
+        out.remove(0);
+    }
+    out
+}
+
+/// The string of a `#[doc = "..."]` attribute, None for other attributes
+fn doc_attribute(attr: &str) -> Option<String> {
+    let inner = attr.trim_left_matches('#').trim_left_matches('!').trim_left_matches('[')
+        .trim_right_matches(']').trim();
+    if !inner.starts_with("doc") {
+        return None;
+    }
+    let value = inner[3..].trim_left();
+    if !value.starts_with('=') {
+        return None;
+    }
+    let lit = value[1..].trim();
+    if lit.starts_with('r') {
+        // raw string, no escapes
+        let hashes = lit[1..].chars().take_while(|&c| c == '#').count();
+        let body = &lit[1 + hashes..];
+        if body.len() < 2 + hashes || !body.starts_with('"') {
+            return None;
+        }
+        return Some(body[1..body.len() - 1 - hashes].to_owned());
+    }
+    if lit.len() < 2 || !lit.starts_with('"') || !lit.ends_with('"') {
+        return None;
+    }
+    let mut out = String::new();
+    let mut chars = lit[1..lit.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\n') => {
+                // line continuation, skips the next line's indentation
+                while chars.clone().next().map_or(false, char::is_whitespace) {
+                    chars.next();
+                }
+            }
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    Some(out)
+}
+
+#[test]
+fn finds_line_block_and_attribute_docs() {
+    let src = "/// Line one\n///\n///     code\n#[inline]\npub fn foo() {}\n\
+               /**\n * Block\n * docs\n */\nstruct Bar;\n\
+               #[doc = \"Attr \\\"docs\\\"\"]\n#[derive(Debug,\n         Clone)]\nenum Baz {}\n\
+               //// not docs\nfn qux() {}\n";
+    assert_eq!("Line one\n\n    code", find_docs(src, src.find("foo").unwrap()));
+    assert_eq!("Block\ndocs", find_docs(src, src.find("Bar").unwrap()));
+    assert_eq!("Attr \"docs\"", find_docs(src, src.find("Baz").unwrap()));
+    assert_eq!("", find_docs(src, src.find("qux").unwrap()));
+}
+
+#[test]
+fn finds_module_docs_at_point_zero() {
+    let src = "//! The module\n//! docs\n\n#![allow(dead_code)]\n/// foo docs\nfn foo() {}\n";
+    assert_eq!("The module\ndocs", find_docs(src, 0));
+}
+
+#[test]
+fn ignores_comments_above_non_items() {
+    let src = "fn foo() {\n    /// not an item\n    let a = 1;\n}";
+    assert_eq!("", find_docs(src, src.find("a =").unwrap()));
+}
@@ -0,0 +1,227 @@
+// Impl index
+//
+// nameres::search_for_impls only scans the file a type is defined in. The index lists the `impl`
+// blocks of every module reachable from a crate root, following `mod foo;` declarations the same
+// way match_mod does, keyed by the names of the self type and of the trait. That way methods
+// implemented in other modules and files are found too. It also lists the files it visited, which
+// are the crate's source files.
+
+use ast;
+use core::{self, Session, Src};
+use nameres::{find_possible_crate_root_modules, get_module_file, PATH_SEP};
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone)]
+pub struct ImplEntry {
+    pub filepath: PathBuf,
+    pub start: usize,               // of the `impl` keyword
+    pub end: usize,
+    pub self_path: core::Path,
+    pub trait_path: Option<core::Path>,
+    // the bounds on the self type if the impl is for every type meeting them, e.g. `Display`
+    // for `impl<T: Display> ToString for T`
+    pub blanket_bounds: Option<Vec<String>>,
+}
+
+#[derive(Clone,Default)]
+pub struct ImplIndex {
+    entries: Vec<ImplEntry>,
+    by_self_type: HashMap<String, Vec<usize>>,
+    by_trait: HashMap<String, Vec<usize>>,
+    blanket: Vec<usize>,
+    files: Vec<PathBuf>,
+}
+
+impl ImplIndex {
+    /// The impls whose self type is named name, e.g. both `impl Foo` and `impl Bar for Foo`
+    pub fn for_self_type(&self, name: &str) -> Vec<&ImplEntry> {
+        self.by_self_type.get(name).map_or(Vec::new(), |ixs| ixs.iter().map(|&i| &self.entries[i]).collect())
+    }
+
+    /// The impls of the trait named name
+    pub fn for_trait(&self, name: &str) -> Vec<&ImplEntry> {
+        self.by_trait.get(name).map_or(Vec::new(), |ixs| ixs.iter().map(|&i| &self.entries[i]).collect())
+    }
+
+    /// The impls for every type that meets their bounds, e.g. `impl<T: Display> ToString for T`
+    pub fn blanket_impls(&self) -> Vec<&ImplEntry> {
+        self.blanket.iter().map(|&i| &self.entries[i]).collect()
+    }
+
+    /// The files of the modules reachable from the crate root, the root first
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn add(&mut self, entry: ImplEntry) {
+        let i = self.entries.len();
+        if entry.blanket_bounds.is_some() {
+            self.blanket.push(i);
+        }
+        if let Some(name) = last_segment(&entry.self_path) {
+            self.by_self_type.entry(name).or_insert_with(Vec::new).push(i);
+        }
+        if let Some(name) = entry.trait_path.as_ref().and_then(last_segment) {
+            self.by_trait.entry(name).or_insert_with(Vec::new).push(i);
+        }
+        self.entries.push(entry);
+    }
+}
+
+fn last_segment(path: &core::Path) -> Option<String> {
+    path.segments.last().map(|seg| seg.name.clone())
+}
+
+/// The crate root of the crate that filepath is in, or filepath itself if it isn't in one
+pub fn crate_root(filepath: &Path) -> PathBuf {
+    let dir = filepath.parent().unwrap_or(Path::new(""));
+    find_possible_crate_root_modules(dir).into_iter().nth(0)
+        .unwrap_or(filepath.to_path_buf())
+}
+
+/// Builds the index of the impls in the crate rooted at root.
+///
+/// Crates under RUST_SRC_PATH get an empty index: their impls are found by the single file scan
+/// (or, for blanket impls, build_module_impl_index) and indexing them would mean reading most of
+/// std on every query.
+pub fn build_impl_index(root: &Path, session: &Session) -> ImplIndex {
+    let mut index = ImplIndex::default();
+    if is_in_rust_src(root) {
+        return index;
+    }
+    debug!("build_impl_index: indexing the crate at {:?}", root);
+    let searchdir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut visited = HashSet::new();
+    index_module_file(root, &searchdir, session, &mut visited, &mut index);
+    index
+}
+
+fn index_module_file(filepath: &Path, searchdir: &Path, session: &Session,
+                     visited: &mut HashSet<PathBuf>, index: &mut ImplIndex) {
+    if !visited.insert(filepath.to_path_buf()) {
+        return;
+    }
+    index.files.push(filepath.to_path_buf());
+    let msrc = session.load_file_and_mask_comments(filepath);
+    index_scope(msrc, 0, msrc.len(), filepath, searchdir, session, visited, index);
+}
+
+fn index_scope(msrc: Src, scopestart: usize, scopeend: usize, filepath: &Path, searchdir: &Path,
+               session: &Session, visited: &mut HashSet<PathBuf>, index: &mut ImplIndex) {
+    let scopesrc = msrc.from_to(scopestart, scopeend);
+    for (start, end) in scopesrc.iter_stmts() {
+        let blob = &scopesrc[start..end];
+        let item = strip_item_prefix(blob);
+        let itemstart = scopestart + start + (blob.len() - item.len());
+
+        if item.starts_with("impl") {
+            if let Some(entry) = impl_entry(item, itemstart, scopestart + end, filepath) {
+                index.add(entry);
+            }
+        } else if item.starts_with("mod ") {
+            let name: String = item[4..].trim_left().chars()
+                .take_while(|&c| c.is_alphanumeric() || c == '_').collect();
+            match item.find('{') {
+                Some(n) => {
+                    // files declared in an inline module are in a subdirectory named after it
+                    let inner = itemstart + n + 1;
+                    index_scope(msrc, inner, scopestart + end - 1, filepath, &searchdir.join(&name),
+                                session, visited, index);
+                }
+                None => if let Some(modpath) = get_module_file(&name, searchdir) {
+                    let subdir = modpath.parent().unwrap_or(Path::new("")).to_path_buf();
+                    index_module_file(&modpath, &subdir, session, visited, index);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the index of the impls in a single module, without its submodules: the one whose
+/// items start at scopestart in filepath (0 for a module in its own file).
+///
+/// Unlike build_impl_index this indexes crates under RUST_SRC_PATH too, e.g. to find the blanket
+/// impls next to a std trait.
+pub fn build_module_impl_index(filepath: &Path, scopestart: usize, session: &Session) -> ImplIndex {
+    let mut index = ImplIndex::default();
+    let msrc = session.load_file_and_mask_comments(filepath);
+    let scopesrc = msrc.from(scopestart);
+    for (start, end) in scopesrc.iter_stmts() {
+        let blob = &scopesrc[start..end];
+        let item = strip_item_prefix(blob);
+        if item.starts_with("impl") {
+            let itemstart = scopestart + start + (blob.len() - item.len());
+            if let Some(entry) = impl_entry(item, itemstart, scopestart + end, filepath) {
+                index.add(entry);
+            }
+        }
+    }
+    index
+}
+
+// The entry for the impl item between start and end
+fn impl_entry(item: &str, start: usize, end: usize, filepath: &Path) -> Option<ImplEntry> {
+    let n = match item.find('{') {
+        Some(n) => n,
+        None => return None
+    };
+    let mut decl = item[..n + 1].to_owned();
+    decl.push_str("}");
+    let implres = ast::parse_impl(decl.clone());
+    let trait_path = implres.trait_path;
+    implres.name_path.map(|self_path| {
+        let blanket_bounds = if self_path.segments.len() == 1 && !self_path.global {
+            let generics = ast::parse_generics(decl);
+            generics.generic_args.iter().position(|arg| *arg == self_path.segments[0].name)
+                .map(|i| generics.generic_bounds[i].clone())
+        } else {
+            None
+        };
+        ImplEntry {
+            filepath: filepath.to_path_buf(),
+            start: start,
+            end: end,
+            self_path: self_path,
+            trait_path: trait_path,
+            blanket_bounds: blanket_bounds,
+        }
+    })
+}
+
+// skips the visibility and `unsafe` before an item's keyword
+fn strip_item_prefix(blob: &str) -> &str {
+    let mut item = blob;
+    for prefix in &["pub ", "unsafe "] {
+        if item.starts_with(prefix) {
+            item = item[prefix.len()..].trim_left();
+        }
+    }
+    item
+}
+
+fn is_in_rust_src(filepath: &Path) -> bool {
+    env::var("RUST_SRC_PATH").map(|paths| {
+        paths.split(PATH_SEP).any(|srcpath| !srcpath.is_empty() && filepath.starts_with(srcpath))
+    }).unwrap_or(false)
+}
+
+#[test]
+fn indexes_impls_by_self_type_and_trait() {
+    let mut index = ImplIndex::default();
+    let path = |s: &str| core::Path::from_vec(false, s.split("::").collect());
+    index.add(ImplEntry { filepath: PathBuf::from("a.rs"), start: 0, end: 10,
+                          self_path: path("Foo"), trait_path: None, blanket_bounds: None });
+    index.add(ImplEntry { filepath: PathBuf::from("b.rs"), start: 0, end: 10,
+                          self_path: path("foo::Foo"), trait_path: Some(path("fmt::Display")),
+                          blanket_bounds: None });
+    index.add(ImplEntry { filepath: PathBuf::from("b.rs"), start: 20, end: 30,
+                          self_path: path("T"), trait_path: Some(path("ToString")),
+                          blanket_bounds: Some(vec!["Display".to_owned()]) });
+    assert_eq!(2, index.for_self_type("Foo").len());
+    assert_eq!("b.rs", index.for_trait("Display")[0].filepath.to_str().unwrap());
+    assert!(index.for_self_type("Bar").is_empty());
+    assert_eq!(20, index.blanket_impls()[0].start);
+}
@@ -6,6 +6,7 @@ extern crate syntex_syntax;
 extern crate toml;
 extern crate env_logger;
 extern crate typed_arena;
+extern crate rustc_serialize;
 
 #[macro_use]
 pub mod testutils;
@@ -20,3 +21,13 @@ pub mod util;
 pub mod matchers;
 pub mod snippets;
 pub mod cargo;
+pub mod lsp;
+pub mod references;
+pub mod signature;
+pub mod outline;
+pub mod symbols;
+pub mod rename;
+pub mod ranking;
+pub mod docs;
+pub mod arms;
+pub mod impls;
@@ -0,0 +1,634 @@
+// Language Server Protocol frontend
+//
+// Speaks just enough of LSP (JSON-RPC over stdio) to drive completion, hover, navigation,
+// outlines and renames from an editor without spawning a racer process per keystroke. Buffers
+// sent via didOpen/didChange are cached in a single FileCache that lives as long as the server.
+
+use core::{self, Encoding, FileCache, Match, MatchType, Session};
+use docs;
+use outline::{self, OutlineItem};
+use references::{self, ReferenceKind};
+use rename;
+use scopes;
+use signature;
+
+use rustc_serialize::json::{self, Json, ToJson};
+
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND: i64 = -32601;
+const INTERNAL_ERROR: i64 = -32603;
+const REQUEST_FAILED: i64 = -32803;
+
+// LSP TextDocumentSyncKind.Full: clients send the whole buffer on every change
+const SYNC_FULL: u64 = 1;
+
+// the largest message body read_message accepts, so a bad Content-Length can't exhaust memory
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Runs the server until the client sends `exit` or closes the input stream.
+pub fn run<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> io::Result<()> {
+    let cache = FileCache::new();
+    while let Some(msg) = try!(read_message(input)) {
+        let method = msg.find("method").and_then(|m| m.as_string()).unwrap_or("").to_owned();
+        let params = msg.find("params").cloned().unwrap_or(Json::Null);
+        debug!("lsp: received {}", method);
+
+        match msg.find("id").cloned() {
+            // requests need a response
+            Some(id) => {
+                // open buffers are never stale, but files pulled in from disk may be
+                cache.invalidate_stale_files();
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    handle_request(&method, &params, &cache)
+                }));
+                let response = match res {
+                    Ok(Ok(result)) => response_ok(id, result),
+                    Ok(Err((code, message))) => response_err(id, code, &message),
+                    Err(_) => response_err(id, INTERNAL_ERROR, "racer panicked handling the request"),
+                };
+                try!(write_message(output, &response));
+            }
+            // ... notifications don't
+            None => {
+                if method == "exit" {
+                    break;
+                }
+                handle_notification(&method, &params, &cache);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_request<'c>(method: &str, params: &Json,
+                      cache: &'c FileCache<'c>) -> Result<Json, (i64, String)> {
+    match method {
+        "initialize" => Ok(server_capabilities()),
+        "shutdown" => Ok(Json::Null),
+        "textDocument/completion" => Ok(completion(params, cache)),
+        "completionItem/resolve" => Ok(resolve_completion_item(params, cache)),
+        "textDocument/definition" => Ok(definition(params, cache)),
+        "textDocument/hover" => Ok(hover(params, cache)),
+        "textDocument/references" => Ok(references(params, cache)),
+        "textDocument/signatureHelp" => Ok(signature_help(params, cache)),
+        "textDocument/documentSymbol" => Ok(document_symbols(params, cache)),
+        "textDocument/rename" => rename(params, cache),
+        _ => Err((METHOD_NOT_FOUND, format!("unsupported method {}", method)))
+    }
+}
+
+fn handle_notification<'c>(method: &str, params: &Json, cache: &'c FileCache<'c>) {
+    match method {
+        "textDocument/didOpen" => {
+            let doc = params.find("textDocument");
+            let path = doc.and_then(|d| d.find("uri")).and_then(|u| u.as_string()).and_then(uri_to_path);
+            let text = doc.and_then(|d| d.find("text")).and_then(|t| t.as_string());
+            if let (Some(path), Some(text)) = (path, text) {
+                cache.cache_file_contents(&path, text);
+            }
+        }
+        "textDocument/didChange" => {
+            let path = document_path(params);
+            // with full sync the last change holds the complete buffer
+            let text = params.find("contentChanges")
+                .and_then(|c| c.as_array())
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.find("text"))
+                .and_then(|t| t.as_string());
+            if let (Some(path), Some(text)) = (path, text) {
+                cache.cache_file_contents(&path, text);
+            }
+        }
+        "textDocument/didClose" => {
+            if let Some(path) = document_path(params) {
+                cache.uncache_file(&path);
+            }
+        }
+        _ => debug!("lsp: ignoring notification {}", method)
+    }
+}
+
+fn server_capabilities() -> Json {
+    let mut completion = json::Object::new();
+    completion.insert("triggerCharacters".to_owned(), vec![".".to_owned(), ":".to_owned()].to_json());
+    // docs are only looked up for the item the editor shows, see resolve_completion_item
+    completion.insert("resolveProvider".to_owned(), true.to_json());
+
+    let mut caps = json::Object::new();
+    caps.insert("textDocumentSync".to_owned(), SYNC_FULL.to_json());
+    caps.insert("completionProvider".to_owned(), Json::Object(completion));
+    caps.insert("definitionProvider".to_owned(), true.to_json());
+    caps.insert("hoverProvider".to_owned(), true.to_json());
+    caps.insert("referencesProvider".to_owned(), true.to_json());
+    caps.insert("documentSymbolProvider".to_owned(), true.to_json());
+    caps.insert("renameProvider".to_owned(), true.to_json());
+
+    let mut signature_help = json::Object::new();
+    signature_help.insert("triggerCharacters".to_owned(), vec!["(".to_owned(), ",".to_owned()].to_json());
+    caps.insert("signatureHelpProvider".to_owned(), Json::Object(signature_help));
+
+    let mut result = json::Object::new();
+    result.insert("capabilities".to_owned(), Json::Object(caps));
+    Json::Object(result)
+}
+
+fn completion<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Json::Null
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let point = match params.find("position").and_then(|p| position_to_point(&src, p)) {
+        Some(point) => point,
+        None => return Json::Null
+    };
+
+    let items: Vec<Json> = core::complete_from_file(&src, &path, point, &session)
+        .map(|m| completion_item(&m))
+        .collect();
+    Json::Array(items)
+}
+
+fn completion_item(m: &Match) -> Json {
+    let mut item = json::Object::new();
+    item.insert("label".to_owned(), m.matchstr.to_json());
+    item.insert("kind".to_owned(), completion_item_kind(m.mtype).to_json());
+    item.insert("detail".to_owned(), m.contextstr.to_json());
+    // where the item is, for resolve_completion_item to read its docs from
+    let mut data = json::Object::new();
+    data.insert("filepath".to_owned(), m.filepath.to_string_lossy().to_json());
+    data.insert("point".to_owned(), m.point.to_json());
+    item.insert("data".to_owned(), Json::Object(data));
+    Json::Object(item)
+}
+
+/// Adds the docs to a completion item from completion, when the editor is about to show it
+fn resolve_completion_item<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let mut item = match params.as_object() {
+        Some(item) => item.clone(),
+        None => return params.clone()
+    };
+    let filepath = params.find_path(&["data", "filepath"]).and_then(|p| p.as_string()).map(PathBuf::from);
+    let point = params.find_path(&["data", "point"]).and_then(|p| p.as_u64());
+    if let (Some(filepath), Some(point)) = (filepath, point) {
+        let session = Session::from_path(cache, &filepath, &filepath);
+        let src = session.load_file(&filepath);
+        if point as usize <= src.len() {
+            let docs = docs::find_docs(&src, point as usize);
+            if !docs.is_empty() {
+                item.insert("documentation".to_owned(), docs.to_json());
+            }
+        }
+    }
+    Json::Object(item)
+}
+
+fn definition<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Json::Null
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let point = match params.find("position").and_then(|p| position_to_point(&src, p)) {
+        Some(point) => point,
+        None => return Json::Null
+    };
+
+    core::find_definition(&src, &path, point, &session)
+        .and_then(|m| location(&m, &session))
+        .unwrap_or(Json::Null)
+}
+
+fn hover<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Json::Null
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let point = match params.find("position").and_then(|p| position_to_point(&src, p)) {
+        Some(point) => point,
+        None => return Json::Null
+    };
+
+    let ty = match core::find_type_at(&src, &path, point, &session) {
+        Some(ty) => ty,
+        None => return Json::Null
+    };
+    // the type, then the signature of the item defining it
+    let mut contents = vec![ty.to_string().to_json()];
+    if let Some(m) = ty.defining_match() {
+        let mut signature = json::Object::new();
+        signature.insert("language".to_owned(), "rust".to_json());
+        signature.insert("value".to_owned(), m.contextstr.to_json());
+        contents.push(Json::Object(signature));
+    }
+    // and the docs of the item named at the point
+    if let Some(m) = core::find_definition(&src, &path, point, &session) {
+        if !m.docs.is_empty() {
+            contents.push(m.docs.to_json());
+        }
+    }
+
+    let mut result = json::Object::new();
+    result.insert("contents".to_owned(), Json::Array(contents));
+    Json::Object(result)
+}
+
+fn references<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Json::Null
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let point = match params.find("position").and_then(|p| position_to_point(&src, p)) {
+        Some(point) => point,
+        None => return Json::Null
+    };
+    let include_declaration = params.find_path(&["context", "includeDeclaration"])
+        .and_then(|b| b.as_boolean())
+        .unwrap_or(true);
+
+    let (def, refs) = match references::find_references(&src, &path, point, &session) {
+        Some(res) => res,
+        None => return Json::Array(Vec::new())
+    };
+    let locations = refs.iter()
+        .filter(|r| include_declaration || r.kind != ReferenceKind::Definition)
+        .filter_map(|r| span_location(&r.filepath, r.point, def.matchstr.len(), &session))
+        .collect();
+    Json::Array(locations)
+}
+
+fn signature_help<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Json::Null
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let point = match params.find("position").and_then(|p| position_to_point(&src, p)) {
+        Some(point) => point,
+        None => return Json::Null
+    };
+
+    let sig = match signature::find_signature_help(&src, &path, point, &session) {
+        Some(sig) => sig,
+        None => return Json::Null
+    };
+    let parameters = sig.args.iter().map(|arg| {
+        let mut param = json::Object::new();
+        let label = if arg.ty.is_empty() { arg.name.clone() } else { format!("{}: {}", arg.name, arg.ty) };
+        param.insert("label".to_owned(), label.to_json());
+        param.insert("documentation".to_owned(), arg.docs.to_json());
+        Json::Object(param)
+    }).collect();
+
+    let mut signature = json::Object::new();
+    signature.insert("label".to_owned(), sig.signature.to_json());
+    signature.insert("documentation".to_owned(), sig.docs.to_json());
+    signature.insert("parameters".to_owned(), Json::Array(parameters));
+
+    let mut result = json::Object::new();
+    result.insert("signatures".to_owned(), Json::Array(vec![Json::Object(signature)]));
+    result.insert("activeSignature".to_owned(), 0.to_json());
+    result.insert("activeParameter".to_owned(), sig.active_arg.to_json());
+    Json::Object(result)
+}
+
+fn document_symbols<'c>(params: &Json, cache: &'c FileCache<'c>) -> Json {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Json::Null
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let symbols = outline::outline(&path, &session).iter()
+        .map(|item| document_symbol(item, &src))
+        .collect();
+    Json::Array(symbols)
+}
+
+fn document_symbol(item: &OutlineItem, src: &str) -> Json {
+    let range = |start, end| {
+        let mut range = json::Object::new();
+        range.insert("start".to_owned(), point_to_position(src, start));
+        range.insert("end".to_owned(), point_to_position(src, end));
+        Json::Object(range)
+    };
+    let children = item.children.iter().map(|child| document_symbol(child, src)).collect();
+
+    let mut symbol = json::Object::new();
+    symbol.insert("name".to_owned(), item.name.to_json());
+    symbol.insert("kind".to_owned(), symbol_kind(item.mtype).to_json());
+    symbol.insert("range".to_owned(), range(item.start, item.end));
+    symbol.insert("selectionRange".to_owned(), range(item.point, item.point + item.name.len()));
+    symbol.insert("children".to_owned(), Json::Array(children));
+    Json::Object(symbol)
+}
+
+fn rename<'c>(params: &Json, cache: &'c FileCache<'c>) -> Result<Json, (i64, String)> {
+    let path = match document_path(params) {
+        Some(path) => path,
+        None => return Ok(Json::Null)
+    };
+    let session = Session::from_path(cache, &path, &path);
+    let src = session.load_file(&path);
+    let (point, newname) = match (params.find("position").and_then(|p| position_to_point(&src, p)),
+                                  params.find("newName").and_then(|n| n.as_string())) {
+        (Some(point), Some(newname)) => (point, newname),
+        _ => return Ok(Json::Null)
+    };
+
+    let edits = try!(rename::rename(&src, &path, point, newname, &session)
+        .map_err(|e| (REQUEST_FAILED, e.to_string())));
+
+    // a WorkspaceEdit, with the text edits grouped by document
+    let mut changes = json::Object::new();
+    for edit in &edits {
+        let edit_src = session.load_file(&edit.filepath);
+        let mut range = json::Object::new();
+        range.insert("start".to_owned(), point_to_position(&edit_src, edit.start));
+        range.insert("end".to_owned(), point_to_position(&edit_src, edit.end));
+        let mut text_edit = json::Object::new();
+        text_edit.insert("range".to_owned(), Json::Object(range));
+        text_edit.insert("newText".to_owned(), edit.replacement.to_json());
+
+        let uri = path_to_uri(&edit.filepath);
+        if let Json::Array(ref mut text_edits) = *changes.entry(uri).or_insert(Json::Array(Vec::new())) {
+            text_edits.push(Json::Object(text_edit));
+        }
+    }
+    let mut result = json::Object::new();
+    result.insert("changes".to_owned(), Json::Object(changes));
+    Ok(Json::Object(result))
+}
+
+fn location(m: &Match, session: &Session) -> Option<Json> {
+    let (start, end) = m.name_range;
+    span_location(&m.filepath, start, end - start, session)
+}
+
+fn span_location(filepath: &Path, point: usize, len: usize, session: &Session) -> Option<Json> {
+    let src = session.load_file(filepath);
+    if point > src.len() {
+        return None;
+    }
+    let end = ::std::cmp::min(point + len, src.len());
+
+    let mut range = json::Object::new();
+    range.insert("start".to_owned(), point_to_position(&src, point));
+    range.insert("end".to_owned(), point_to_position(&src, end));
+
+    let mut loc = json::Object::new();
+    loc.insert("uri".to_owned(), path_to_uri(filepath).to_json());
+    loc.insert("range".to_owned(), Json::Object(range));
+    Some(Json::Object(loc))
+}
+
+fn completion_item_kind(mtype: MatchType) -> u64 {
+    // values of the LSP CompletionItemKind enumeration
+    match mtype {
+        MatchType::Function => 3,
+        MatchType::StructField => 5,
+        MatchType::Let | MatchType::IfLet | MatchType::WhileLet |
+        MatchType::For | MatchType::MatchArm | MatchType::FnArg |
+        MatchType::Static => 6,
+        MatchType::Trait => 8,
+        MatchType::Module | MatchType::Crate => 9,
+        MatchType::Impl => 7,
+        MatchType::Enum => 13,
+        MatchType::Builtin => 14,
+        MatchType::EnumVariant => 20,
+        MatchType::Const => 21,
+        MatchType::Struct => 22,
+        MatchType::Type | MatchType::TypeParameter => 25,
+        MatchType::Macro => 15,
+    }
+}
+
+fn symbol_kind(mtype: MatchType) -> u64 {
+    // values of the LSP SymbolKind enumeration
+    match mtype {
+        MatchType::Module | MatchType::Crate => 2,
+        MatchType::Impl => 5,
+        MatchType::StructField => 8,
+        MatchType::Enum => 10,
+        MatchType::Trait => 11,
+        MatchType::Function | MatchType::Macro => 12,
+        MatchType::Let | MatchType::IfLet | MatchType::WhileLet |
+        MatchType::For | MatchType::MatchArm | MatchType::FnArg |
+        MatchType::Static => 13,
+        MatchType::Const => 14,
+        MatchType::EnumVariant => 22,
+        MatchType::Struct => 23,
+        MatchType::Type | MatchType::TypeParameter | MatchType::Builtin => 26,
+    }
+}
+
+fn document_path(params: &Json) -> Option<PathBuf> {
+    params.find_path(&["textDocument", "uri"])
+        .and_then(|u| u.as_string())
+        .and_then(uri_to_path)
+}
+
+/// Converts an LSP position (0-based line, utf-16 character offset) to a byte offset
+fn position_to_point(src: &str, position: &Json) -> Option<usize> {
+    let (line, character) = match (position.find("line").and_then(|l| l.as_u64()),
+                                   position.find("character").and_then(|c| c.as_u64())) {
+        (Some(line), Some(character)) => (line as usize, character as usize),
+        _ => return None
+    };
+    if scopes::coords_to_point(src, line + 1, 0) > src.len() {
+        return None;
+    }
+    Some(scopes::encoded_coords_to_point(src, line + 1, character, Encoding::Utf16))
+}
+
+/// Converts a byte offset to an LSP position
+fn point_to_position(src: &str, point: usize) -> Json {
+    let (linenum, character) = scopes::point_to_encoded_coords(src, point, Encoding::Utf16);
+
+    let mut pos = json::Object::new();
+    pos.insert("line".to_owned(), (linenum - 1).to_json());
+    pos.insert("character".to_owned(), character.to_json());
+    Json::Object(pos)
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+    let mut bytes = Vec::new();
+    let raw = &uri["file://".len()..];
+    let mut i = 0;
+    while i < raw.len() {
+        if raw.as_bytes()[i] == b'%' && i + 2 < raw.len() {
+            let hex = ::std::str::from_utf8(&raw.as_bytes()[i+1..i+3]).ok();
+            match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(b) => bytes.push(b),
+                None => return None
+            }
+            i += 3;
+        } else {
+            bytes.push(raw.as_bytes()[i]);
+            i += 1;
+        }
+    }
+    let path = match String::from_utf8(bytes) {
+        Ok(path) => path,
+        Err(_) => return None
+    };
+    // file:///C:/foo on windows
+    if cfg!(windows) && path.starts_with('/') && path[1..].find(':') == Some(1) {
+        return Some(PathBuf::from(&path[1..]));
+    }
+    Some(PathBuf::from(path))
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_str().unwrap().replace('\\', "/");
+    let mut uri = "file://".to_owned();
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for b in path.bytes() {
+        match b {
+            b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' |
+            b'/' | b'-' | b'_' | b'.' | b'~' | b':' => uri.push(b as char),
+            _ => uri.push_str(&format!("%{:02X}", b))
+        }
+    }
+    uri
+}
+
+fn response_ok(id: Json, result: Json) -> Json {
+    let mut obj = json::Object::new();
+    obj.insert("jsonrpc".to_owned(), "2.0".to_json());
+    obj.insert("id".to_owned(), id);
+    obj.insert("result".to_owned(), result);
+    Json::Object(obj)
+}
+
+fn response_err(id: Json, code: i64, message: &str) -> Json {
+    let mut err = json::Object::new();
+    err.insert("code".to_owned(), code.to_json());
+    err.insert("message".to_owned(), message.to_json());
+
+    let mut obj = json::Object::new();
+    obj.insert("jsonrpc".to_owned(), "2.0".to_json());
+    obj.insert("id".to_owned(), id);
+    obj.insert("error".to_owned(), Json::Object(err));
+    Json::Object(obj)
+}
+
+/// Reads one `Content-Length` framed message. Returns None at end of input, and an InvalidData
+/// error for a length that is not a number or is over MAX_MESSAGE_SIZE.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if try!(input.read_line(&mut line)) == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_right();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(n) = header.find(':') {
+            if header[..n].to_lowercase() == "content-length" {
+                let length = match header[n+1..].trim().parse::<usize>() {
+                    Ok(length) => length,
+                    Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                        format!("bad header: {}", header)))
+                };
+                if length > MAX_MESSAGE_SIZE {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                              format!("message too large: {} bytes", length)));
+                }
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let mut body = vec![0u8; content_length.unwrap()];
+    try!(input.read_exact(&mut body));
+    let body = match String::from_utf8(body) {
+        Ok(body) => body,
+        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "message is not utf-8"))
+    };
+    match Json::from_str(&body) {
+        Ok(msg) => Ok(Some(msg)),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))
+    }
+}
+
+pub fn write_message<W: Write>(output: &mut W, msg: &Json) -> io::Result<()> {
+    let body = msg.to_string();
+    try!(write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body));
+    output.flush()
+}
+
+#[test]
+fn converts_file_uris_to_paths() {
+    assert_eq!(Some(PathBuf::from("/home/me/my project/lib.rs")),
+               uri_to_path("file:///home/me/my%20project/lib.rs"));
+    assert_eq!(None, uri_to_path("untitled:Untitled-1"));
+    assert_eq!("file:///home/me/my%20project/lib.rs",
+               path_to_uri(Path::new("/home/me/my project/lib.rs")));
+}
+
+#[test]
+fn converts_utf16_positions() {
+    let src = "fn main() {\n    let µ = \"𝄞\"; µ\n}";
+    let mut pos = json::Object::new();
+    pos.insert("line".to_owned(), 1u64.to_json());
+    pos.insert("character".to_owned(), 18u64.to_json());
+    let point = position_to_point(src, &Json::Object(pos.clone())).unwrap();
+    assert_eq!("µ\n}", &src[point..]);
+    assert_eq!(Json::Object(pos), point_to_position(src, point));
+}
+
+#[test]
+fn reads_framed_messages() {
+    let mut input = io::Cursor::new("Content-Length: 17\r\n\r\n{\"method\":\"exit\"}".as_bytes());
+    let msg = read_message(&mut input).unwrap().unwrap();
+    assert_eq!(Some("exit"), msg.find("method").and_then(|m| m.as_string()));
+    assert!(read_message(&mut input).unwrap().is_none());
+}
+
+#[test]
+fn rejects_bad_content_lengths() {
+    let mut input = io::Cursor::new("Content-Length: 1000000000000\r\n\r\n{}".as_bytes());
+    assert_eq!(io::ErrorKind::InvalidData, read_message(&mut input).unwrap_err().kind());
+    let mut input = io::Cursor::new("Content-Length: seventeen\r\n\r\n{}".as_bytes());
+    assert_eq!(io::ErrorKind::InvalidData, read_message(&mut input).unwrap_err().kind());
+}
+
+#[test]
+fn resolves_docs_of_completion_items() {
+    let cache = FileCache::new();
+    let path = Path::new("/resolves_docs_of_completion_items.rs");
+    cache.cache_file_contents(path, "/// Says hello\nfn hello() {}\nfn main() { hel }");
+    let mut params = json::Object::new();
+    params.insert("textDocument".to_owned(),
+                  Json::from_str(r#"{"uri": "file:///resolves_docs_of_completion_items.rs"}"#).unwrap());
+    params.insert("position".to_owned(),
+                  Json::from_str(r#"{"line": 2, "character": 15}"#).unwrap());
+    let items = completion(&Json::Object(params), &cache);
+    let item = &items.as_array().unwrap()[0];
+    assert_eq!(None, item.find("documentation"));
+    let resolved = resolve_completion_item(item, &cache);
+    assert_eq!(Some("Says hello"), resolved.find("documentation").and_then(|d| d.as_string()));
+}
@@ -2,7 +2,7 @@ use {scopes, typeinf, ast};
 use core::{Match, PathSegment, Src, Session};
 use util::{symbol_matches, txt_matches, find_ident_end, is_ident_char, char_at};
 use nameres::{get_module_file, get_crate_file, resolve_path};
-use core::SearchType::{self, StartsWith, ExactMatch};
+use core::SearchType::{self, StartsWith, ExactMatch, Fuzzy};
 use core::MatchType::{self, Let, Module, Function, Struct, Type, Trait, Enum, EnumVariant,
                       Const, Static, IfLet, WhileLet, For, Macro};
 use core::Namespace::BothNamespaces;
@@ -77,9 +77,14 @@ fn find_keyword(src: &str, pattern: &str, search: &str, search_type: SearchType,
         return None;
     }
 
+    if let Fuzzy = search_type {
+        let end = find_ident_end(src, start);
+        return if symbol_matches(search_type, search, &src[start..end]) { Some(start) } else { None };
+    }
+
     if src[start..].starts_with(search) {
         match search_type {
-            StartsWith => Some(start),
+            StartsWith | Fuzzy => Some(start),
             ExactMatch => {
                 if src.len() > start+search.len() &&
                     !is_ident_char(char_at(src, start + search.len())) {
@@ -94,6 +99,15 @@ fn find_keyword(src: &str, pattern: &str, search: &str, search_type: SearchType,
     }
 }
 
+// The name at the start that find_keyword returned, which for an exact match is searchstr
+fn found_name<'a>(blob: &'a str, start: usize, searchstr: &'a str, search_type: SearchType) -> &'a str {
+    match search_type {
+        ExactMatch => searchstr, // already checked in find_keyword
+        StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())],
+        Fuzzy => &blob[start..find_ident_end(blob, start)]
+    }
+}
+
 fn is_const_fn(src: &str, blobstart: usize, blobend: usize) -> bool {
     src[blobstart..blobend].contains("const fn")
 }
@@ -112,11 +126,14 @@ fn match_pattern_start(src: &str, blobstart: usize, blobend: usize,
                 matchstr: s.to_owned(),
                 filepath: filepath.to_path_buf(),
                 point: blobstart+start,
+                name_range: (blobstart + start, blobstart + start + s.len()),
+                item_range: (blobstart, blobend),
                 local: local,
                 mtype: mtype,
                 contextstr: first_line(blob),
                 generic_args: Vec::new(),
-                generic_types: Vec::new()
+                generic_types: Vec::new(),
+                docs: String::new()
             })
         }
     }
@@ -154,11 +171,14 @@ fn match_pattern_let(msrc: &str, blobstart: usize, blobend: usize,
                 out.push(Match { matchstr: s.to_owned(),
                                    filepath: filepath.to_path_buf(),
                                    point: blobstart + start,
+                                   name_range: (blobstart + start, blobstart + end),
+                                   item_range: (blobstart, blobend),
                                    local: local,
                                    mtype: mtype,
                                    contextstr: first_line(blob),
                                    generic_args: Vec::new(),
-                                   generic_types: Vec::new()
+                                   generic_types: Vec::new(),
+                                   docs: String::new()
                          });
                 if let ExactMatch = search_type {
                     break;
@@ -203,11 +223,13 @@ pub fn match_for(msrc: &str, blobstart: usize, blobend: usize,
             out.push(Match { matchstr: s.to_owned(),
                              filepath: filepath.to_path_buf(),
                              point: blobstart + start,
+                             name_range: (blobstart + start, blobstart + end),
+                             item_range: (blobstart, blobend),
                              local: local,
                              mtype: For,
                              contextstr: first_line(blob),
                              generic_args: Vec::new(),
-                             generic_types: Vec::new() });
+                             generic_types: Vec::new(), docs: String::new() });
         }
     }
     out
@@ -223,10 +245,16 @@ pub fn match_extern_crate(msrc: &str, blobstart: usize, blobend: usize,
     let mut res = None;
     let blob = &msrc[blobstart..blobend];
 
-    if txt_matches(search_type, &format!("extern crate {};", searchstr), blob) &&
-        !(txt_matches(search_type, &format!("extern crate {} as", searchstr), blob))
-        || (blob.starts_with("extern crate") &&
-            txt_matches(search_type, &format!("as {}", searchstr), blob)) {
+    let candidate = match search_type {
+        // the name is only known once the blob is parsed
+        Fuzzy => blob.starts_with("extern crate"),
+        ExactMatch | StartsWith =>
+            txt_matches(search_type, &format!("extern crate {};", searchstr), blob) &&
+            !(txt_matches(search_type, &format!("extern crate {} as", searchstr), blob))
+            || (blob.starts_with("extern crate") &&
+                txt_matches(search_type, &format!("as {}", searchstr), blob))
+    };
+    if candidate {
 
         debug!("found an extern crate: |{}|", blob);
 
@@ -247,6 +275,9 @@ pub fn match_extern_crate(msrc: &str, blobstart: usize, blobend: usize,
 
         if let Some(ref name) = extern_crate.name {
             debug!("extern crate {}", name);
+            if !symbol_matches(search_type, searchstr, name) {
+                return None;
+            }
 
             let realname =
                 if let Some(ref realname) = extern_crate.realname {
@@ -258,11 +289,14 @@ pub fn match_extern_crate(msrc: &str, blobstart: usize, blobend: usize,
                 res = Some(Match { matchstr: name.clone(),
                                   filepath: cratepath.to_path_buf(),
                                   point: 0,
+                                  name_range: (0, 0),
+                                  item_range: (0, 0),
                                   local: false,
                                   mtype: Module,
                                   contextstr: cratepath.to_str().unwrap().to_owned(),
                                   generic_args: Vec::new(),
-                                  generic_types: Vec::new()
+                                  generic_types: Vec::new(),
+                                  docs: String::new()
                 });
             });
         }
@@ -276,10 +310,7 @@ pub fn match_mod(msrc: Src, blobstart: usize, blobend: usize,
     let blob = &msrc[blobstart..blobend];
     if let Some(start) = find_keyword(blob, "mod", searchstr, search_type, local) {
         debug!("found a module: |{}|", blob);
-        let l = match search_type {
-            ExactMatch => searchstr, // already checked in find_keyword
-            StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-        };
+        let l = found_name(blob, start, searchstr, search_type);
 
         if blob.find('{').is_some() {
             debug!("found an inline module!");
@@ -288,11 +319,14 @@ pub fn match_mod(msrc: Src, blobstart: usize, blobend: usize,
                 matchstr: l.to_owned(),
                 filepath: filepath.to_path_buf(),
                 point: blobstart + start,
+                name_range: (blobstart + start, blobstart + start + l.len()),
+                item_range: (blobstart, blobend),
                 local: false,
                 mtype: Module,
                 contextstr: filepath.to_str().unwrap().to_owned(),
                 generic_args: Vec::new(),
-                generic_types: Vec::new()
+                generic_types: Vec::new(),
+                docs: String::new()
             })
         } else {
             // get internal module nesting
@@ -309,11 +343,14 @@ pub fn match_mod(msrc: Src, blobstart: usize, blobend: usize,
                     matchstr: l.to_owned(),
                     filepath: modpath.to_path_buf(),
                     point: 0,
+                    name_range: (0, 0),
+                    item_range: (0, 0),
                     local: false,
                     mtype: Module,
                     contextstr: modpath.to_str().unwrap().to_owned(),
                     generic_args: Vec::new(),
-                    generic_types: Vec::new()
+                    generic_types: Vec::new(),
+                    docs: String::new()
                 })
             }
         }
@@ -326,10 +363,7 @@ pub fn match_struct(msrc: &str, blobstart: usize, blobend: usize,
                     local: bool) -> Option<Match> {
     let blob = &msrc[blobstart..blobend];
     if let Some(start) = find_keyword(blob, "struct", searchstr, search_type, local) {
-        let l = match search_type {
-            ExactMatch => searchstr, // already checked in find_keyword
-            StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-        };
+        let l = found_name(blob, start, searchstr, search_type);
         debug!("found a struct |{}|", l);
 
         // Parse generics
@@ -348,11 +382,14 @@ pub fn match_struct(msrc: &str, blobstart: usize, blobend: usize,
             matchstr: l.to_owned(),
             filepath: filepath.to_path_buf(),
             point: blobstart + start,
+            name_range: (blobstart + start, blobstart + start + l.len()),
+            item_range: (blobstart, blobend),
             local: local,
             mtype: Struct,
             contextstr: first_line(blob),
             generic_args: generics.generic_args,
-            generic_types: Vec::new()
+            generic_types: Vec::new(),
+            docs: String::new()
         })
     } else {
         None
@@ -364,20 +401,20 @@ pub fn match_type(msrc: &str, blobstart: usize, blobend: usize,
                   local: bool) -> Option<Match> {
     let blob = &msrc[blobstart..blobend];
     if let Some(start) = find_keyword(blob, "type", searchstr, search_type, local) {
-        let l = match search_type {
-            ExactMatch => searchstr, // already checked in find_keyword
-            StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-        };
+        let l = found_name(blob, start, searchstr, search_type);
         debug!("found!! a type {}", l);
         Some(Match {
             matchstr: l.to_owned(),
             filepath: filepath.to_path_buf(),
             point: blobstart + start,
+            name_range: (blobstart + start, blobstart + start + l.len()),
+            item_range: (blobstart, blobend),
             local: local,
             mtype: Type,
             contextstr: first_line(blob),
             generic_args: Vec::new(),
-            generic_types: Vec::new()
+            generic_types: Vec::new(),
+            docs: String::new()
         })
     } else {
         None
@@ -389,20 +426,20 @@ pub fn match_trait(msrc: &str, blobstart: usize, blobend: usize,
                    local: bool) -> Option<Match> {
     let blob = &msrc[blobstart..blobend];
     if let Some(start) = find_keyword(blob, "trait", searchstr, search_type, local) {
-        let l = match search_type {
-            ExactMatch => searchstr, // already checked in find_keyword
-            StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-        };
+        let l = found_name(blob, start, searchstr, search_type);
         debug!("found!! a trait {}", l);
         Some(Match {
             matchstr: l.to_owned(),
             filepath: filepath.to_path_buf(),
             point: blobstart + start,
+            name_range: (blobstart + start, blobstart + start + l.len()),
+            item_range: (blobstart, blobend),
             local: local,
             mtype: Trait,
             contextstr: first_line(blob),
             generic_args: Vec::new(),
-            generic_types: Vec::new()
+            generic_types: Vec::new(),
+            docs: String::new()
         })
     } else {
         None
@@ -420,16 +457,19 @@ pub fn match_enum_variants(msrc: &str, blobstart: usize, blobend: usize,
         let parsed_enum = ast::parse_enum(blob.to_owned());
 
         for (name, offset) in parsed_enum.values.into_iter() {
-            if (&name).starts_with(searchstr) {
+            if symbol_matches(search_type, searchstr, &name) {
                 let m = Match {
                     matchstr: name.clone(),
                     filepath: filepath.to_path_buf(),
                     point: blobstart + offset,
+                    name_range: (blobstart + offset, blobstart + offset + name.len()),
+                    item_range: (blobstart + offset, blobstart + scopes::end_of_list_item(blob, offset)),
                     local: local,
                     mtype: EnumVariant,
                     contextstr: first_line(&blob[offset..]),
                     generic_args: Vec::new(),
-                    generic_types: Vec::new()
+                    generic_types: Vec::new(),
+                    docs: String::new()
                 };
                 out.push(m);
             }
@@ -443,10 +483,7 @@ pub fn match_enum(msrc: &str, blobstart: usize, blobend: usize,
                   local: bool) -> Option<Match> {
     let blob = &msrc[blobstart..blobend];
     if let Some(start) = find_keyword(blob, "enum", searchstr, search_type, local) {
-        let l = match search_type {
-            ExactMatch => searchstr, // already checked in find_keyword
-            StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-        };
+        let l = found_name(blob, start, searchstr, search_type);
         debug!("found!! an enum |{}|", l);
         // Parse generics
         let end = blob.find('{').or(blob.find(';'))
@@ -457,11 +494,14 @@ pub fn match_enum(msrc: &str, blobstart: usize, blobend: usize,
             matchstr: l.to_owned(),
             filepath: filepath.to_path_buf(),
             point: blobstart + start,
+            name_range: (blobstart + start, blobstart + start + l.len()),
+            item_range: (blobstart, blobend),
             local: local,
             mtype: Enum,
             contextstr: first_line(blob),
             generic_args: generics.generic_args,
-            generic_types: Vec::new()
+            generic_types: Vec::new(),
+            docs: String::new()
         })
     } else {
         None
@@ -508,8 +548,9 @@ pub fn match_use(msrc: &str, blobstart: usize, blobend: usize,
                 path.segments.push(seg);
                 debug!("found a glob: now searching for {:?}", path);
                 let iter_path = resolve_path(&path, filepath, blobstart, search_type, BothNamespaces, session);
-                if let StartsWith = search_type {
-                    return iter_path.collect();
+                match search_type {
+                    StartsWith | Fuzzy => return iter_path.collect(),
+                    ExactMatch => {}
                 }
                 for m in iter_path {
                     out.push(m);
@@ -576,20 +617,20 @@ pub fn match_fn(msrc: &str, blobstart: usize, blobend: usize,
     if let Some(start) = find_keyword(blob, keyword, searchstr, search_type, local) {
         if !typeinf::first_param_is_self(blob) {
             debug!("found a fn starting {}", searchstr);
-            let l = match search_type {
-                ExactMatch => searchstr, // already checked in find_keyword
-                StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-            };
+            let l = found_name(blob, start, searchstr, search_type);
             debug!("found a fn {}", l);
             Some(Match {
                 matchstr: l.to_owned(),
                 filepath: filepath.to_path_buf(),
                 point: blobstart + start,
+                name_range: (blobstart + start, blobstart + start + l.len()),
+                item_range: (blobstart, blobend),
                 local: local,
                 mtype: Function,
                 contextstr: first_line(blob),
                 generic_args: Vec::new(),
-                generic_types: Vec::new()
+                generic_types: Vec::new(),
+                docs: String::new()
             })
         } else {
             None
@@ -606,21 +647,21 @@ pub fn match_macro(msrc: &str, blobstart: usize, blobend: usize,
     let searchstr = searchstr.trim_right_matches('!');
     if let Some(start) = find_keyword(blob, "macro_rules!", searchstr, search_type, local) {
         debug!("found a macro starting {}", searchstr);
-        let l = match search_type {
-            ExactMatch => searchstr, // already checked in find_keyword
-            StartsWith => &blob[start..find_ident_end(blob, start+searchstr.len())]
-        };
+        let l = found_name(blob, start, searchstr, search_type);
         let l = format!("{}!", l);
         debug!("found a macro {}", l);
         Some(Match {
             matchstr: l.to_owned(),
             filepath: filepath.to_path_buf(),
             point: blobstart + start,
+            name_range: (blobstart + start, blobstart + start + l.len() - 1),  // without the !
+            item_range: (blobstart, blobend),
             local: local,
             mtype: Macro,
             contextstr: first_line(blob),
             generic_args: Vec::new(),
-            generic_types: Vec::new()
+            generic_types: Vec::new(),
+            docs: String::new()
         })
     } else {
         None
@@ -1,12 +1,14 @@
 // Name resolution
 
 use {core, ast, matchers, scopes, typeinf};
-use core::SearchType::{self, ExactMatch, StartsWith};
+use core::SearchType::{self, ExactMatch, StartsWith, Fuzzy};
 use core::{Match, Src, Session};
-use core::MatchType::{Module, Function, Struct, Enum, FnArg, Trait, StructField, Impl, MatchArm, Builtin};
+use core::MatchType::{Module, Function, Struct, Enum, FnArg, Trait, StructField, Impl, MatchArm, Builtin,
+                      TypeParameter};
 use core::Namespace::{self, TypeNamespace, ValueNamespace, BothNamespaces};
-use util::{symbol_matches, txt_matches, find_ident_end, path_exists};
+use util::{symbol_matches, txt_matches, find_ident_end, path_exists, same_file};
 use cargo;
+use impls::{self, ImplEntry};
 use std::path::{Path, PathBuf};
 use std::{self, vec};
 
@@ -15,37 +17,76 @@ pub const PATH_SEP: &'static str = ":";
 #[cfg(windows)]
 pub const PATH_SEP: &'static str = ";";
 
-fn search_struct_fields(searchstr: &str, structmatch: &Match,
-                        search_type: SearchType, session: &Session) -> vec::IntoIter<Match> {
+/// The fields of a struct (or enum struct variant) matching searchstr
+pub fn search_struct_fields(searchstr: &str, structmatch: &Match,
+                            search_type: SearchType, session: &Session) -> vec::IntoIter<Match> {
     let src = session.load_file(&structmatch.filepath);
-    let opoint = scopes::find_stmt_start(src, structmatch.point);
-    let structsrc = scopes::end_of_next_scope(&src[opoint.unwrap()..]);
-
-    let fields = ast::parse_struct_fields(structsrc.to_owned(),
-                                          core::Scope::from_match(structmatch));
+    let fields = typeinf::get_struct_fields(structmatch, session);
 
     let mut out = Vec::new();
 
-    for (field, fpos, ty) in fields.into_iter() {
+    for (field, point, ty) in fields.into_iter() {
         if symbol_matches(search_type, searchstr, &field) {
             let contextstr = if let Some(t) = ty {
                 t.to_string()
             } else {
                 field.clone()
             };
+            let name_range = (point, point + field.len());
+            let item_range = (point, scopes::end_of_list_item(&src, point));
             out.push(Match { matchstr: field,
                                 filepath: structmatch.filepath.to_path_buf(),
-                                point: fpos + opoint.unwrap(),
+                                point: point,
+                                name_range: name_range,
+                                item_range: item_range,
                                 local: structmatch.local,
                                 mtype: StructField,
                                 contextstr: contextstr,
-                                generic_args: Vec::new(), generic_types: Vec::new()
+                                generic_args: Vec::new(), generic_types: Vec::new(),
+                                docs: String::new()
             });
         }
     }
     out.into_iter()
 }
 
+/// The variants of an enum matching searchstr
+pub fn search_enum_variants(enummatch: &Match, searchstr: &str, search_type: SearchType,
+                            session: &Session) -> vec::IntoIter<Match> {
+    let filesrc = session.load_file(&enummatch.filepath);
+    let scopestart = scopes::find_stmt_start(filesrc, enummatch.point).unwrap();
+    let scopesrc = filesrc.from(scopestart);
+    let mut out = Vec::new();
+    scopesrc.iter_stmts().nth(0).map(|(blobstart,blobend)| {
+        for m in matchers::match_enum_variants(&filesrc,
+                                               scopestart+blobstart,
+                                               scopestart+blobend,
+                                               searchstr, &enummatch.filepath, search_type, true) {
+            debug!("Found enum variant: {}", m.matchstr);
+            out.push(m);
+        }
+    });
+    out.into_iter()
+}
+
+/// The items matching pathseg in the impls of a struct or enum
+fn search_impl_scopes(m: &Match, pathseg: &core::PathSegment, search_type: SearchType,
+                      namespace: Namespace, session: &Session) -> vec::IntoIter<Match> {
+    let mut out = Vec::new();
+    for m in search_for_impls(m.point, &m.matchstr, &m.filepath, m.local, false, session) {
+        debug!("found impl!! {:?}", m);
+        let src = session.load_file(&m.filepath);
+        // find the opening brace and skip to it.
+        (&src[m.point..]).find("{").map(|n| {
+            let point = m.point + n + 1;
+            for m in search_scope(point, point, src, pathseg, &m.filepath, search_type, m.local, namespace, session) {
+                out.push(m);
+            }
+        });
+    };
+    out.into_iter()
+}
+
 pub fn search_for_impl_methods(match_request: &Match,
                            fieldsearchstr: &str, point: usize,
                            fpath: &Path, local: bool,
@@ -57,8 +98,14 @@ pub fn search_for_impl_methods(match_request: &Match,
     debug!("searching for impl methods |{:?}| |{}| {:?}", match_request, fieldsearchstr, fpath.to_str());
 
     let mut out = Vec::new();
-
-    for m in search_for_impls(point, implsearchstr, fpath, local, true, session) {
+    // the methods of the implemented traits, which are only wanted where the impls don't
+    // override them
+    let mut provided = Vec::new();
+    let mut implemented = Vec::new();
+
+    let impls = search_for_impls(point, implsearchstr, fpath, local, true, session)
+        .chain(search_for_blanket_impls(point, implsearchstr, fpath, local, session));
+    for m in impls {
         debug!("found impl!! |{:?}| looking for methods", m);
 
         if m.matchstr == "Deref" {
@@ -70,30 +117,125 @@ pub fn search_for_impl_methods(match_request: &Match,
         // find the opening brace and skip to it.
         (&src[m.point..]).find("{").map(|n| {
             let point = m.point + n + 1;
-            for m in search_scope_for_methods(point, src, fieldsearchstr, &m.filepath, search_type) {
-                out.push(m);
+            for method in search_scope_for_methods(point, src, fieldsearchstr, &m.filepath, search_type) {
+                if m.mtype == Trait {
+                    provided.push(method);
+                } else {
+                    implemented.push(method.matchstr.clone());
+                    out.push(method);
+                }
             }
         });
     };
+    for method in provided {
+        if !implemented.contains(&method.matchstr) {
+            implemented.push(method.matchstr.clone());
+            out.push(method);
+        }
+    }
+    out.into_iter()
+}
+
+// traits that types implement without an impl
+const AUTO_TRAITS: &'static [&'static str] = &["Sized", "Send", "Sync"];
+
+/// The blanket impls (e.g. `impl<T: Display> ToString for T`) whose bounds the type named
+/// searchstr at pos meets, along with the traits they implement.
+///
+/// Coherence puts a blanket impl in the crate of the trait it implements, so besides the impls in
+/// the type's crate those in the modules of the traits in scope are searched: the ones the type
+/// implements and the ones in the std prelude.
+fn search_for_blanket_impls(pos: usize, searchstr: &str, filepath: &Path, local: bool,
+                            session: &Session) -> vec::IntoIter<Match> {
+    let mut out = Vec::new();
+    let index = session.impl_index(filepath);
+    let mut blanket: Vec<ImplEntry> = index.blanket_impls().into_iter().cloned().collect();
+
+    // only the traits implemented directly count, not those from other blanket impls
+    let traits: Vec<Match> = search_for_impls(pos, searchstr, filepath, local, true, session)
+        .filter(|m| m.mtype == Trait).collect();
+
+    let root = impls::crate_root(filepath);
+    let mut modules = Vec::new();
+    for traitm in traits.iter().chain(session.prelude_traits(filepath).iter()) {
+        // the type's own crate is already indexed
+        if impls::crate_root(&traitm.filepath) == root {
+            continue;
+        }
+        let msrc = session.load_file_and_mask_comments(&traitm.filepath);
+        let module = (traitm.filepath.clone(), scopes::scope_start(msrc, traitm.point));
+        if !modules.contains(&module) {
+            blanket.extend(session.module_impl_index(&module.0, module.1).blanket_impls().into_iter().cloned());
+            modules.push(module);
+        }
+    }
+
+    for entry in blanket {
+        let bounds = entry.blanket_bounds.as_ref().unwrap();
+        let meets_bounds = bounds.iter().all(|bound| {
+            AUTO_TRAITS.contains(&bound.rsplit("::").next().unwrap()) ||
+                resolve_trait(bound, &entry.filepath, entry.start, session).map_or(false, |traitm| {
+                    traits.iter().any(|m| m.point == traitm.point && same_file(&m.filepath, &traitm.filepath))
+                })
+        });
+        if meets_bounds {
+            debug!("search_for_blanket_impls: {} meets the bounds {:?}", searchstr, bounds);
+            let esrc = session.load_file(&entry.filepath);
+            match_impl(&esrc[entry.start..entry.end], entry.start, entry.end,
+                       &entry.self_path.segments[0].name, &entry.filepath, local, true, session, &mut out);
+        }
+    }
     out.into_iter()
 }
 
 fn search_scope_for_methods(point: usize, src: Src, searchstr: &str, filepath: &Path,
                             search_type: SearchType) -> vec::IntoIter<Match> {
+    let mut out = Vec::new();
+    search_scope_for_methods_(point, src, searchstr, filepath, search_type, true, &mut out);
+    out.into_iter()
+}
+
+// expand_macros: whether to search the bodies of the macros invoked in the scope, e.g.
+// `int_impl!` in libcore's `impl i32 { int_impl! { i32, u32, 32, ... } }`
+fn search_scope_for_methods_(point: usize, src: Src, searchstr: &str, filepath: &Path,
+                             search_type: SearchType, expand_macros: bool, out: &mut Vec<Match>) {
     debug!("searching scope for methods {} |{}| {:?}", point, searchstr, filepath.to_str());
 
     let scopesrc = src.from(point);
-    let mut out = Vec::new();
     for (blobstart,blobend) in scopesrc.iter_stmts() {
         let blob = &scopesrc[blobstart..blobend];
-        blob.find("{").map(|n| { // only matches if is a method implementation
-            let signature = &blob[..n -1];
-
-            if txt_matches(search_type, &format!("fn {}", searchstr), signature)
-                && typeinf::first_param_is_self(blob) {
-                debug!("found a method starting |{}| |{}|", searchstr, blob);
+        if expand_macros {
+            if let Some(name) = macro_invocation_name(blob) {
+                for body in macro_arm_bodies(src, name) {
+                    search_scope_for_methods_(body, src, searchstr, filepath, search_type, false, out);
+                }
+                continue;
+            }
+        }
+        // method implementations, and the declarations of trait methods without a default
+        let sigend = blob.find("{").map(|n| n - 1)
+            .or_else(|| if blob.ends_with(';') { Some(blob.len() - 1) } else { None });
+        sigend.map(|n| {
+            let signature = blob[..n].trim_right();
+
+            let start = match search_type {
+                Fuzzy => signature.find("fn ").map(|i| i + 3).and_then(|start| {
+                    let name = &signature[start..find_ident_end(signature, start)];
+                    if symbol_matches(search_type, searchstr, name) { Some(start) } else { None }
+                }),
                 // TODO: parse this properly
-                let start = blob.find(&format!("fn {}", searchstr)).unwrap() + 3;
+                ExactMatch | StartsWith =>
+                    if txt_matches(search_type, &format!("fn {}", searchstr), signature) {
+                        blob.find(&format!("fn {}", searchstr)).map(|i| i + 3)
+                    } else {
+                        None
+                    }
+            };
+            if let Some(start) = start {
+                if !typeinf::first_param_is_self(blob) {
+                    return;
+                }
+                debug!("found a method starting |{}| |{}|", searchstr, blob);
                 let end = find_ident_end(blob, start);
                 let l = &blob[start..end];
                 // TODO: make a better context string for functions
@@ -101,16 +243,60 @@ fn search_scope_for_methods(point: usize, src: Src, searchstr: &str, filepath: &
                            matchstr: l.to_owned(),
                            filepath: filepath.to_path_buf(),
                            point: point + blobstart + start,
+                           name_range: (point + blobstart + start, point + blobstart + end),
+                           item_range: (point + blobstart, point + blobend),
                            local: true,
                            mtype: Function,
                            contextstr: signature.to_owned(),
-                           generic_args: Vec::new(), generic_types: Vec::new()
+                           generic_args: Vec::new(), generic_types: Vec::new(),
+                           docs: String::new()
                 };
                 out.push(m);
             }
         });
     }
-    out.into_iter()
+}
+
+// The name of the macro that blob invokes, e.g. `int_impl` for `int_impl! { i32, ... }`
+fn macro_invocation_name(blob: &str) -> Option<&str> {
+    // (find_ident_end counts the `!` as part of the name)
+    let end = find_ident_end(blob, 0);
+    if end > 1 && blob[..end].ends_with('!') && &blob[..end] != "macro_rules!" {
+        Some(&blob[..end - 1])
+    } else {
+        None
+    }
+}
+
+// The start of the body of each arm of the macro named name that's defined in src, e.g. just
+// after the second brace of `macro_rules! int_impl { ($T:ty) => { ... } }`
+fn macro_arm_bodies(src: Src, name: &str) -> Vec<usize> {
+    let mut out = Vec::new();
+    let pattern = format!("macro_rules! {}", name);
+    for (start, end) in src.iter_stmts() {
+        let blob = &src[start..end];
+        if !blob.starts_with(&pattern) || find_ident_end(blob, "macro_rules! ".len()) != pattern.len() {
+            continue;
+        }
+        let open = match blob.find('{') {
+            Some(open) => open,
+            None => continue
+        };
+        let defstart = start + open + 1;
+        for (armstart, armend) in src.from(defstart).iter_stmts() {
+            // the matcher is in parens and the body in braces: `(...) => { ... }`
+            let arm = &src[defstart + armstart..defstart + armend];
+            let matcherend = if arm.starts_with('(') { scopes::find_closing_paren(arm, 1) } else { 0 };
+            if let Some(arrow) = arm[matcherend..].find("=>") {
+                let bodystart = matcherend + arrow + 2;
+                if arm[bodystart..].trim_left().starts_with('{') {
+                    let brace = bodystart + arm[bodystart..].find('{').unwrap();
+                    out.push(defstart + armstart + brace + 1);
+                }
+            }
+        }
+    }
+    out
 }
 
 
@@ -121,71 +307,93 @@ pub fn search_for_impls(pos: usize, searchstr: &str, filepath: &Path, local: boo
     let src = s.from(pos);
 
     let mut out = Vec::new();
+    let mut scanned = Vec::new();
     for (start, end) in src.iter_stmts() {
         let blob = &src[start..end];
 
         if blob.starts_with("impl") {
-            blob.find("{").map(|n| {
-                let mut decl = (&blob[..n+1]).to_owned();
-                decl.push_str("}");
-                if txt_matches(ExactMatch, searchstr, &decl) {
-                    debug!("impl decl {}", decl);
-                    let implres = ast::parse_impl(decl);
-                    let is_trait_impl = implres.trait_path.is_some();
-
-                    implres.name_path.map(|name_path| {
-                        name_path.segments.last().map(|name| {
-                            if symbol_matches(ExactMatch, searchstr, &name.name) {
-                                let m = Match {
-                                    matchstr: name.name.clone(),
-                                    filepath: filepath.to_path_buf(),
-                                    point: pos + start + 5,
-                                    // items in trait impls have no "pub" but are
-                                    // still accessible from other modules
-                                    local: local || is_trait_impl,
-                                    mtype: Impl,
-                                    contextstr: "".into(),
-                                    generic_args: Vec::new(),
-                                    generic_types: Vec::new()
-                                };
-                                out.push(m);
-                            }
-                        });
-                    });
+            scanned.push(pos + start);
+            match_impl(blob, pos + start, pos + end, searchstr, filepath, local, include_traits,
+                       session, &mut out);
+        }
+    }
 
-                    // find trait
-                    if include_traits && is_trait_impl {
-                        let trait_path = implres.trait_path.unwrap();
-                        let mut m = resolve_path(&trait_path,
-                                             filepath, pos + start, ExactMatch, TypeNamespace,
-                                             session).nth(0);
-                        debug!("found trait |{:?}| {:?}", trait_path, m);
-
-                        if let Some(ref mut m) = m {
-                            if m.matchstr == "Deref" {
-                                let impl_block = &blob[n..];
-
-                                if let Some(pos) = impl_block.find("=") {
-                                    let deref_type_start = n + pos + 1;
-
-                                    if let Some(pos) = blob[deref_type_start..].find(";") {
-                                        let deref_type_end = deref_type_start + pos;
-                                        let deref_type = &blob[deref_type_start..deref_type_end].to_owned().trim().to_owned();
-                                        debug!("Deref to {} found", deref_type);
-
-                                        m.generic_args = vec![deref_type.clone()];
-                                    };
-                                };
-                            }
-                        }
+    // impls elsewhere in the crate, e.g. in another module or file
+    for entry in session.impl_index(filepath).for_self_type(searchstr) {
+        if scanned.contains(&entry.start) && same_file(&entry.filepath, filepath) {
+            continue;
+        }
+        // only if the impl's self type is the type being searched, not another type of that name
+        let is_same_type = resolve_path(&entry.self_path, &entry.filepath, entry.start,
+                                        ExactMatch, TypeNamespace, session).nth(0)
+            .map_or(false, |m| m.point == pos && same_file(&m.filepath, filepath));
+        if is_same_type {
+            debug!("search_for_impls: found an impl in {:?}", entry.filepath);
+            let esrc = session.load_file(&entry.filepath);
+            match_impl(&esrc[entry.start..entry.end], entry.start, entry.end, searchstr,
+                       &entry.filepath, local, include_traits, session, &mut out);
+        }
+    }
+    out.into_iter()
+}
 
-                        m.map(|m| out.push(m));
+// Pushes the Impl match for the impl block between start and end if it's an impl of searchstr,
+// and the trait it implements if include_traits is set
+fn match_impl(blob: &str, start: usize, end: usize, searchstr: &str, filepath: &Path,
+              local: bool, include_traits: bool, session: &Session, out: &mut Vec<Match>) {
+    blob.find("{").map(|n| {
+        let mut decl = (&blob[..n+1]).to_owned();
+        decl.push_str("}");
+        if txt_matches(ExactMatch, searchstr, &decl) {
+            debug!("impl decl {}", decl);
+            let implres = ast::parse_impl(decl);
+            let is_trait_impl = implres.trait_path.is_some();
+
+            implres.name_path.map(|name_path| {
+                name_path.segments.last().map(|name| {
+                    if symbol_matches(ExactMatch, searchstr, &name.name) {
+                        let m = Match {
+                            matchstr: name.name.clone(),
+                            filepath: filepath.to_path_buf(),
+                            point: start + 5,
+                            name_range: (start + 5, start + 5 + name.name.len()),
+                            item_range: (start, end),
+                            // items in trait impls have no "pub" but are
+                            // still accessible from other modules
+                            local: local || is_trait_impl,
+                            mtype: Impl,
+                            contextstr: "".into(),
+                            generic_args: Vec::new(),
+                            generic_types: Vec::new(),
+                            docs: String::new()
+                        };
+                        out.push(m);
                     }
-                }
+                });
             });
+
+            // find trait
+            if include_traits && is_trait_impl {
+                let trait_path = implres.trait_path.unwrap();
+                let mut m = resolve_path(&trait_path,
+                                     filepath, start, ExactMatch, TypeNamespace,
+                                     session).nth(0);
+                debug!("found trait |{:?}| {:?}", trait_path, m);
+
+                if let Some(ref mut m) = m {
+                    if m.matchstr == "Deref" {
+                        let msrc = session.load_file_and_mask_comments(filepath);
+                        if let Some(target) = typeinf::get_assoc_type_in_impl(msrc, start, filepath, "Target") {
+                            debug!("Deref to {} found", target.path);
+                            m.generic_args = vec![target.path.to_string()];
+                        }
+                    }
+                }
+
+                m.map(|m| out.push(m));
+            }
         }
-    }
-    out.into_iter()
+    });
 }
 
 // scope headers include fn decls, if let, while let etc..
@@ -278,11 +486,14 @@ fn search_scope_headers(point: usize, scopestart: usize, msrc: Src, searchstr: &
                                     matchstr: s.to_owned(),
                                     filepath: filepath.to_path_buf(),
                                     point: start,
+                                    name_range: (start, end),
+                                    item_range: (lhs_start, matchstart + arm),
                                     local: true,
                                     mtype: MatchArm,
                                     contextstr: lhs.trim().to_owned(),
                                     generic_args: Vec::new(),
-                                    generic_types: Vec::new()
+                                    generic_types: Vec::new(),
+                                    docs: String::new()
                     });
                     if let SearchType::ExactMatch = search_type {
                         break;
@@ -334,11 +545,14 @@ fn search_fn_args(fnstart: usize, open_brace_pos: usize, msrc: &str,
                                 matchstr: s.to_owned(),
                                 filepath: filepath.to_path_buf(),
                                 point: fnstart + start - impl_header_len,
+                                name_range: (fnstart + start - impl_header_len, fnstart + end - impl_header_len),
+                                item_range: (fnstart + start - impl_header_len, fnstart + end - impl_header_len),
                                 local: local,
                                 mtype: FnArg,
                                 contextstr: s.to_owned(),
                                 generic_args: Vec::new(),
-                                generic_types: Vec::new()
+                                generic_types: Vec::new(),
+                                docs: String::new()
                 };
                 debug!("search_fn_args matched: {:?}", m);
                 out.push(m);
@@ -372,11 +586,14 @@ pub fn do_file_search(searchstr: &str, currentdir: &Path) -> vec::IntoIter<Match
                                        matchstr: (&fname[3..]).to_owned(),
                                        filepath: filepath.to_path_buf(),
                                        point: 0,
+                                       name_range: (0, 0),
+                                       item_range: (0, 0),
                                        local: false,
                                        mtype: Module,
                                        contextstr: (&fname[3..]).to_owned(),
                                        generic_args: Vec::new(),
-                                       generic_types: Vec::new()
+                                       generic_types: Vec::new(),
+                                       docs: String::new()
                         };
                         out.push(m);
                     }
@@ -391,11 +608,14 @@ pub fn do_file_search(searchstr: &str, currentdir: &Path) -> vec::IntoIter<Match
                                            matchstr: fname.to_owned(),
                                            filepath: filepath.to_path_buf(),
                                            point: 0,
+                                           name_range: (0, 0),
+                                           item_range: (0, 0),
                                            local: false,
                                            mtype: Module,
                                            contextstr: filepath.to_str().unwrap().to_owned(),
                                            generic_args: Vec::new(),
-                                           generic_types: Vec::new()
+                                           generic_types: Vec::new(),
+                                           docs: String::new()
                             };
                             out.push(m);
                         }
@@ -406,11 +626,14 @@ pub fn do_file_search(searchstr: &str, currentdir: &Path) -> vec::IntoIter<Match
                                        matchstr: (&fname[..(fname.len()-3)]).to_owned(),
                                        filepath: fpath_buf.clone(),
                                        point: 0,
+                                       name_range: (0, 0),
+                                       item_range: (0, 0),
                                        local: false,
                                        mtype: Module,
                                        contextstr: fpath_buf.to_str().unwrap().to_owned(),
                                        generic_args: Vec::new(),
-                                       generic_types: Vec::new()
+                                       generic_types: Vec::new(),
+                                       docs: String::new()
                         };
                         out.push(m);
                     }
@@ -499,9 +722,9 @@ pub fn get_crate_file(name: &str, from_path: &Path) -> Option<PathBuf> {
         return Some(p);
     }
 
-    let srcpaths = std::env::var("RUST_SRC_PATH").unwrap();
+    let srcpaths = std::env::var("RUST_SRC_PATH").unwrap_or("".into());
     let v = (&srcpaths).split(PATH_SEP).collect::<Vec<_>>();
-    for srcpath in v.into_iter() {
+    for srcpath in v.into_iter().filter(|srcpath| !srcpath.is_empty()) {
         {
             // try lib<name>/lib.rs, like in the rust source dir
             let cratelibname = format!("lib{}", name);
@@ -633,18 +856,25 @@ pub fn search_scope(start: usize, point: usize, src: Src,
                 out.push(Match { matchstr: "core".into(),
                                   filepath: cratepath.to_path_buf(),
                                   point: 0,
+                                  name_range: (0, 0),
+                                  item_range: (0, 0),
                                   local: false,
                                   mtype: Module,
                                   contextstr: cratepath.to_str().unwrap().to_owned(),
                                   generic_args: Vec::new(),
-                                  generic_types: Vec::new()
+                                  generic_types: Vec::new(),
+                                  docs: String::new()
                 });
             });
         }
 
         // Optimisation: if the search string is not in the blob and it is not
         // a 'use glob', this cannot match so fail fast!
-        if blob.find(searchstr.trim_right_matches('!')).is_none() {
+        let could_match = match search_type {
+            Fuzzy => txt_matches(search_type, searchstr.trim_right_matches('!'), blob),
+            ExactMatch | StartsWith => blob.find(searchstr.trim_right_matches('!')).is_some()
+        };
+        if !could_match {
             continue;
         }
 
@@ -787,6 +1017,16 @@ pub fn search_prelude_file(pathseg: &core::PathSegment, search_type: SearchType,
     out.into_iter()
 }
 
+/// The traits that the prelude of the std crate rooted at stdlib brings into scope
+pub fn search_prelude_traits(stdlib: &Path, session: &Session) -> Vec<Match> {
+    let all = core::PathSegment { name: String::new(), types: Vec::new() };
+    resolve_path(&core::Path::from_vec(false, vec!["prelude", "v1"]), stdlib, 0, ExactMatch,
+                 TypeNamespace, session).nth(0).map_or(Vec::new(), |v1| {
+        search_next_scope(v1.point, &all, &v1.filepath, StartsWith, false, TypeNamespace, session)
+            .filter(|m| m.mtype == Trait).collect()
+    })
+}
+
 pub fn resolve_path_with_str(path: &core::Path, filepath: &Path, pos: usize,
                                    search_type: SearchType, namespace: Namespace,
                                    session: &Session) -> vec::IntoIter<Match> {
@@ -794,25 +1034,13 @@ pub fn resolve_path_with_str(path: &core::Path, filepath: &Path, pos: usize,
 
     let mut out = Vec::new();
 
-    // HACK
-    if path.segments.len() == 1 && path.segments[0].name == "str" {
-        debug!("{:?} == {:?}", path.segments[0], "str");
-
-        if let Some(module) = resolve_path(&core::Path::from_vec(true, vec!["std","str"]),
-                                           filepath, pos, search_type, namespace,
-                                           session).nth(0) {
-            out.push(Match {
-                matchstr: "str".into(),
-                filepath: module.filepath.clone(),
-                point: 0,
-                local: false,
-                mtype: Builtin,
-                contextstr: "str".into(),
-                generic_args: vec![],
-                generic_types: vec![]
-            });
-        }
-        
+    let primitive = match namespace {
+        ValueNamespace => None,
+        _ if path.global || path.segments.len() != 1 => None,
+        _ => resolve_primitive(&path.segments[0].name, filepath, session)
+    };
+    if let Some(m) = primitive {
+        out.push(m);
     } else {
         for m in resolve_path(path, filepath, pos, search_type, namespace, session) {
             out.push(m);
@@ -824,6 +1052,51 @@ pub fn resolve_path_with_str(path: &core::Path, filepath: &Path, pos: usize,
     out.into_iter()
 }
 
+// The crates and modules that the inherent impls of the primitive types are in
+const PRIMITIVE_MODULES: &'static [(&'static str, &'static str, &'static str)] = &[
+    ("str", "std", "str"), ("[T]", "std", "slice"), ("char", "std", "char"),
+    ("bool", "std", "bool"), ("f32", "std", "f32"), ("f64", "std", "f64"),
+    ("i8", "core", "num"), ("i16", "core", "num"), ("i32", "core", "num"),
+    ("i64", "core", "num"), ("isize", "core", "num"), ("u8", "core", "num"),
+    ("u16", "core", "num"), ("u32", "core", "num"), ("u64", "core", "num"),
+    ("usize", "core", "num")];
+
+/// A Builtin match for the primitive type named name (`[T]` for slices), if it is one and the
+/// module with its impls can be found. Its point is where the search for the impls starts.
+pub fn resolve_primitive(name: &str, filepath: &Path, session: &Session) -> Option<Match> {
+    let (cratename, modname) = match PRIMITIVE_MODULES.iter().find(|&&(primitive, _, _)| primitive == name) {
+        Some(&(_, cratename, modname)) => (cratename, modname),
+        None => return None
+    };
+    let cratepath = match get_crate_file(cratename, filepath) {
+        Some(cratepath) => cratepath,
+        None => return None
+    };
+    resolve_path(&core::Path::from_vec(false, vec![modname]),
+                 &cratepath, 0, ExactMatch, TypeNamespace, session).nth(0).map(|module| {
+        // a module in its own file is all body
+        let point = if module.point == 0 {
+            0
+        } else {
+            let msrc = session.load_file_and_mask_comments(&module.filepath);
+            msrc[module.point..].find('{').map_or(module.point, |n| module.point + n + 1)
+        };
+        Match {
+            matchstr: name.to_owned(),
+            filepath: module.filepath.clone(),
+            point: point,
+            name_range: (point, point),
+            item_range: (point, point),
+            local: false,
+            mtype: Builtin,
+            contextstr: name.to_owned(),
+            generic_args: vec![],
+            generic_types: vec![],
+            docs: String::new()
+        }
+    })
+}
+
 thread_local!(pub static SEARCH_STACK: Vec<Search> = Vec::new());
 
 #[derive(PartialEq,Debug)]
@@ -854,19 +1127,19 @@ pub fn resolve_name(pathseg: &core::PathSegment, filepath: &Path, pos: usize,
     debug!("resolve_name {} {:?} {} {:?} {:?}", searchstr, filepath.to_str(), pos, search_type, namespace);
 
     let msrc = session.load_file_and_mask_comments(filepath);
-    let is_exact_match = match search_type { ExactMatch => true, StartsWith => false };
-
-    if (is_exact_match && &searchstr[..] == "std") ||
-       (!is_exact_match && "std".starts_with(searchstr)) {
+    if symbol_matches(search_type, searchstr, "std") {
         get_crate_file("std", filepath).map(|cratepath| {
             out.push(Match {
                         matchstr: "std".into(),
                         filepath: cratepath.to_path_buf(),
                         point: 0,
+                        name_range: (0, 0),
+                        item_range: (0, 0),
                         local: false,
                         mtype: Module,
                         contextstr: cratepath.to_str().unwrap().to_owned(),
-                        generic_args: Vec::new(), generic_types: Vec::new()
+                        generic_args: Vec::new(), generic_types: Vec::new(),
+                        docs: String::new()
             });
         });
 
@@ -997,33 +1270,16 @@ pub fn resolve_path(path: &core::Path, filepath: &Path, pos: usize,
                     let pathseg = &path.segments[len-1];
                     debug!("searching an enum '{}' (whole path: {:?}) searchtype: {:?}", m.matchstr, path, search_type);
 
-                    let filesrc = session.load_file(&m.filepath);
-                    let scopestart = scopes::find_stmt_start(filesrc, m.point).unwrap();
-                    let scopesrc = filesrc.from(scopestart);
-                    scopesrc.iter_stmts().nth(0).map(|(blobstart,blobend)| {
-                        for m in matchers::match_enum_variants(&filesrc,
-                                                               scopestart+blobstart,
-                                                               scopestart+blobend,
-                                                      &pathseg.name, &m.filepath, search_type, true) {
-                            debug!("Found enum variant: {}", m.matchstr);
-                            out.push(m);
-                        }
-                    });
+                    for m in search_enum_variants(&m, &pathseg.name, search_type, session) {
+                        out.push(m);
+                    }
+                    // and associated items, e.g. constructors
+                    out.extend(search_impl_scopes(&m, pathseg, search_type, namespace, session));
                 }
                 Struct => {
                     debug!("found a struct. Now need to look for impl");
-                    for m in search_for_impls(m.point, &m.matchstr, &m.filepath, m.local, false, session) {
-                        debug!("found impl!! {:?}", m);
-                        let pathseg = &path.segments[len-1];
-                        let src = session.load_file(&m.filepath);
-                        // find the opening brace and skip to it.
-                        (&src[m.point..]).find("{").map(|n| {
-                            let point = m.point + n + 1;
-                            for m in search_scope(point, point, src, pathseg, &m.filepath, search_type, m.local, namespace, session) {
-                                out.push(m);
-                            }
-                        });
-                    };
+                    let pathseg = &path.segments[len-1];
+                    out.extend(search_impl_scopes(&m, pathseg, search_type, namespace, session));
                 }
                 _ => ()
             }
@@ -1056,11 +1312,14 @@ pub fn do_external_search(path: &[&str], filepath: &Path, pos: usize, search_typ
                            matchstr: searchstr.to_owned(),
                            filepath: path.to_path_buf(),
                            point: 0,
+                           name_range: (0, 0),
+                           item_range: (0, 0),
                            local: false,
                            mtype: Module,
                            contextstr: path.to_str().unwrap().to_owned(),
                            generic_args: Vec::new(),
-                           generic_types: Vec::new()
+                           generic_types: Vec::new(),
+                           docs: String::new()
                        });
         });
     } else {
@@ -1143,19 +1402,65 @@ pub fn search_for_field_or_method(context: Match, searchstr: &str, search_type:
         },
         Trait => {
             debug!("got a trait, looking for methods {}", m.matchstr);
-            let src = session.load_file(&m.filepath);
-            (&src[m.point..]).find("{").map(|n| {
-                let point = m.point + n + 1;
-                for m in search_scope_for_methods(point, src, searchstr, &m.filepath, search_type) {
-                    out.push(m);
+            search_trait_methods(&m, searchstr, search_type, session, &mut Vec::new(), &mut out);
+        }
+        TypeParameter => {
+            debug!("got a type parameter, looking for the methods of its bounds {:?}", m.generic_args);
+            let mut visited = Vec::new();
+            for bound in &m.generic_args {
+                if let Some(traitm) = resolve_trait(bound, &m.filepath, m.point, session) {
+                    search_trait_methods(&traitm, searchstr, search_type, session, &mut visited, &mut out);
                 }
-            });
+            }
         }
-        _ => { debug!("WARN!! context wasn't a Struct, Enum, Builtin or Trait {:?}",m);}
+        _ => { debug!("WARN!! context wasn't a Struct, Enum, Builtin, Trait or TypeParameter {:?}",m);}
     };
     out.into_iter()
 }
 
+// The methods of the trait and of its supertraits. visited holds the traits already searched.
+fn search_trait_methods(traitm: &Match, searchstr: &str, search_type: SearchType, session: &Session,
+                        visited: &mut Vec<(PathBuf, usize)>, out: &mut Vec<Match>) {
+    let key = (traitm.filepath.clone(), traitm.point);
+    if visited.contains(&key) {
+        return;
+    }
+    visited.push(key);
+
+    let src = session.load_file(&traitm.filepath);
+    (&src[traitm.point..]).find("{").map(|n| {
+        let point = traitm.point + n + 1;
+        out.extend(search_scope_for_methods(point, src, searchstr, &traitm.filepath, search_type));
+    });
+
+    let msrc = session.load_file_and_mask_comments(&traitm.filepath);
+    let start = match scopes::find_stmt_start(msrc, traitm.point) {
+        Some(start) => start,
+        None => return
+    };
+    if !msrc[start..].contains('{') {
+        return;
+    }
+    let decl = typeinf::generate_skeleton_for_parsing(&msrc[start..]);
+    for supertrait in ast::parse_trait(decl).supertraits {
+        if let Some(superm) = resolve_trait(&supertrait, &traitm.filepath, start, session) {
+            search_trait_methods(&superm, searchstr, search_type, session, visited, out);
+        }
+    }
+}
+
+// Resolves a trait path as written in a bound, e.g. `io::Read`
+fn resolve_trait(path: &str, filepath: &Path, pos: usize, session: &Session) -> Option<Match> {
+    let mut v = path.split("::").collect::<Vec<_>>();
+    let global = v[0] == "";
+    if global {
+        v.remove(0);
+    }
+    resolve_path_with_str(&core::Path::from_vec(global, v), filepath, pos, ExactMatch,
+                          TypeNamespace, session)
+        .find(|m| m.mtype == Trait)
+}
+
 fn search_for_deref_matches(impl_match: &Match, type_match: &Match, fieldsearchstr: &str, fpath: &Path, session: &Session) -> vec::IntoIter<Match>
 {
     debug!("Found a Deref Implementation for {}, Searching for Methods on the Deref Type", type_match.matchstr);
@@ -0,0 +1,237 @@
+// Document outline
+//
+// Lists the items of a file as a tree. Each statement is parsed on its own, so a half written
+// item only loses its own body rather than the whole outline.
+
+use ast::string_to_crate;
+use core::{MatchType, Session, Src};
+use util::find_ident_occurrences;
+
+use std::path::Path;
+
+use syntex_syntax::ast;
+use syntex_syntax::codemap::{BytePos, Span};
+use syntex_syntax::print::pprust;
+
+#[derive(Debug,Clone)]
+pub struct OutlineItem {
+    pub name: String,
+    pub mtype: MatchType,
+    pub point: usize,      // start of the name
+    pub start: usize,      // start of the item
+    pub end: usize,        // end of the item
+    pub public: bool,
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    fn offset_by(mut self, offset: usize) -> OutlineItem {
+        self.point += offset;
+        self.start += offset;
+        self.end += offset;
+        self.children = self.children.into_iter().map(|c| c.offset_by(offset)).collect();
+        self
+    }
+}
+
+// The kind of block a statement is in, which decides how it's parsed
+#[derive(Clone,Copy)]
+enum Body {
+    Module,
+    Impl,
+    Trait,
+}
+
+/// Lists the items in the file, with the items inside modules, impls, traits, structs and
+/// enums as children.
+pub fn outline(filepath: &Path, session: &Session) -> Vec<OutlineItem> {
+    let msrc = session.load_file_and_mask_comments(filepath);
+    outline_body(msrc, Body::Module)
+}
+
+fn outline_body(msrc: Src, body: Body) -> Vec<OutlineItem> {
+    let mut out = Vec::new();
+    for (start, end) in msrc.iter_stmts() {
+        let blob = &msrc[start..end];
+        let items = if is_mod_declaration(blob) {
+            // the parser would go looking for the module's file
+            parse_skeleton(msrc.from_to(start, end), body).into_iter().collect()
+        } else {
+            parse_items(blob, body).unwrap_or_else(|| {
+                // couldn't parse it, try again without the body
+                parse_skeleton(msrc.from_to(start, end), body).into_iter().collect()
+            })
+        };
+        out.extend(items.into_iter().map(|item| item.offset_by(start)));
+    }
+    out
+}
+
+// e.g. `mod foo;`
+fn is_mod_declaration(blob: &str) -> bool {
+    blob.ends_with(';') && !blob.contains('{') && !find_ident_occurrences(blob, "mod").is_empty()
+}
+
+/// Parses the item header of a blob that doesn't parse as a whole, outlining the contents of
+/// modules, impls and traits statement by statement. `mod foo;` is parsed as an empty module.
+fn parse_skeleton(blob: Src, body: Body) -> Option<OutlineItem> {
+    if is_mod_declaration(&blob) {
+        let skeleton = format!("{}{{}}", &blob[..blob.len() - 1]);
+        return parse_items(&skeleton, body)
+            .and_then(|items| items.into_iter().nth(0))
+            .map(|mut item| { item.end = blob.len(); item });
+    }
+
+    let brace = match blob.find('{') {
+        Some(brace) => brace,
+        None => return None
+    };
+    let skeleton = format!("{}{{}}", &blob[..brace]);
+    let mut item = match parse_items(&skeleton, body).and_then(|items| items.into_iter().nth(0)) {
+        Some(item) => item,
+        None => return None
+    };
+
+    item.end = blob.len();
+    let inner = match item.mtype {
+        MatchType::Module => Some(Body::Module),
+        MatchType::Impl => Some(Body::Impl),
+        MatchType::Trait => Some(Body::Trait),
+        _ => None
+    };
+    if let Some(inner) = inner {
+        let bodyend = if blob.ends_with('}') { blob.len() - 1 } else { blob.len() };
+        item.children = outline_body(blob.from_to(brace + 1, bodyend), inner).into_iter()
+            .map(|child| child.offset_by(brace + 1))
+            .collect();
+    }
+    Some(item)
+}
+
+/// Parses a blob of source as items, with positions relative to the start of the blob
+fn parse_items(blob: &str, body: Body) -> Option<Vec<OutlineItem>> {
+    // impl and trait items need their container to parse
+    let prefix = match body {
+        Body::Module => "",
+        Body::Impl => "impl X {",
+        Body::Trait => "trait X {",
+    };
+    let suffix = if prefix.is_empty() { "" } else { "}" };
+    let krate = match string_to_crate(format!("{}{}{}", prefix, blob, suffix)) {
+        Some(krate) => krate,
+        None => return None
+    };
+    let conv = Converter { src: blob, shift: prefix.len() };
+
+    let items = match body {
+        Body::Module => krate.module.items.iter().filter_map(|item| conv.item(item)).collect(),
+        Body::Impl => match krate.module.items.get(0).map(|item| &item.node) {
+            Some(&ast::ItemImpl(_, _, _, _, _, ref items)) =>
+                items.iter().filter_map(|item| conv.impl_item(item, false)).collect(),
+            _ => return None
+        },
+        Body::Trait => match krate.module.items.get(0).map(|item| &item.node) {
+            Some(&ast::ItemTrait(_, _, _, ref items)) =>
+                items.iter().map(|item| conv.trait_item(item, true)).collect(),
+            _ => return None
+        },
+    };
+    Some(items)
+}
+
+// Converts syntex items into OutlineItems, `shift` is the length of any text that was added in
+// front of src to parse it
+struct Converter<'a> {
+    src: &'a str,
+    shift: usize,
+}
+
+impl<'a> Converter<'a> {
+    fn new_item(&self, name: String, mtype: MatchType, span: Span,
+                public: bool, children: Vec<OutlineItem>) -> OutlineItem {
+        let BytePos(lo) = span.lo;
+        let BytePos(hi) = span.hi;
+        let start = (lo as usize).saturating_sub(self.shift);
+        let end = ::std::cmp::min((hi as usize).saturating_sub(self.shift), self.src.len());
+        let point = find_ident_occurrences(&self.src[start..end], &name).into_iter().nth(0)
+            .map_or(start, |i| start + i);
+        OutlineItem {
+            name: name,
+            mtype: mtype,
+            point: point,
+            start: start,
+            end: end,
+            public: public,
+            children: children,
+        }
+    }
+
+    fn item(&self, item: &ast::Item) -> Option<OutlineItem> {
+        let name = item.ident.name.to_string();
+        let public = item.vis == ast::Visibility::Public;
+        let (mtype, children) = match item.node {
+            ast::ItemMod(ref m) =>
+                (MatchType::Module, m.items.iter().filter_map(|i| self.item(i)).collect()),
+            ast::ItemStruct(ref data, _) =>
+                (MatchType::Struct, self.fields(data)),
+            ast::ItemEnum(ref def, _) => {
+                let variants = def.variants.iter().map(|v| {
+                    let name = v.node.name.name.to_string();
+                    self.new_item(name, MatchType::EnumVariant, v.span, public, self.fields(&v.node.data))
+                }).collect();
+                (MatchType::Enum, variants)
+            }
+            ast::ItemTrait(_, _, _, ref items) =>
+                (MatchType::Trait, items.iter().map(|i| self.trait_item(i, public)).collect()),
+            ast::ItemImpl(_, _, _, ref otrait, ref ty, ref items) => {
+                // trait impl items are as visible as the trait
+                let children = items.iter()
+                    .filter_map(|i| self.impl_item(i, otrait.is_some()))
+                    .collect();
+                let name = match *otrait {
+                    Some(ref t) => format!("{} for {}", pprust::path_to_string(&t.path),
+                                           pprust::ty_to_string(ty)),
+                    None => pprust::ty_to_string(ty)
+                };
+                return Some(self.new_item(name, MatchType::Impl, item.span, public, children));
+            }
+            ast::ItemFn(..) => (MatchType::Function, Vec::new()),
+            ast::ItemConst(..) => (MatchType::Const, Vec::new()),
+            ast::ItemStatic(..) => (MatchType::Static, Vec::new()),
+            ast::ItemTy(..) => (MatchType::Type, Vec::new()),
+            ast::ItemMac(ref mac) if pprust::path_to_string(&mac.node.path) == "macro_rules" &&
+                                     !name.is_empty() => (MatchType::Macro, Vec::new()),
+            _ => return None
+        };
+        Some(self.new_item(name, mtype, item.span, public, children))
+    }
+
+    fn fields(&self, data: &ast::VariantData) -> Vec<OutlineItem> {
+        data.fields().iter().enumerate().map(|(i, field)| {
+            // tuple fields are named by their ordinal, since self.0 works
+            let name = field.node.ident().map_or(i.to_string(), |ident| ident.name.to_string());
+            let public = field.node.kind.visibility() == ast::Visibility::Public;
+            self.new_item(name, MatchType::StructField, field.span, public, Vec::new())
+        }).collect()
+    }
+
+    fn impl_item(&self, item: &ast::ImplItem, public: bool) -> Option<OutlineItem> {
+        let mtype = match item.node {
+            ast::ImplItemKind::Const(..) => MatchType::Const,
+            ast::ImplItemKind::Method(..) => MatchType::Function,
+            ast::ImplItemKind::Type(..) => MatchType::Type,
+            ast::ImplItemKind::Macro(..) => return None,
+        };
+        let public = public || item.vis == ast::Visibility::Public;
+        Some(self.new_item(item.ident.name.to_string(), mtype, item.span, public, Vec::new()))
+    }
+
+    fn trait_item(&self, item: &ast::TraitItem, public: bool) -> OutlineItem {
+        let mtype = match item.node {
+            ast::ConstTraitItem(..) => MatchType::Const,
+            ast::MethodTraitItem(..) => MatchType::Function,
+            ast::TypeTraitItem(..) => MatchType::Type,
+        };
+        self.new_item(item.ident.name.to_string(), mtype, item.span, public, Vec::new())
+    }
+}
@@ -0,0 +1,126 @@
+// Completion ranking
+//
+// The scope walk finds matches in whatever order it visits scopes, and often finds the same item
+// more than once (e.g. through a `use` and again through the crate root). This drops the
+// duplicates and puts the most likely matches first.
+
+use core::{Match, MatchType, SearchType, Session};
+use nameres::{find_possible_crate_root_modules, PATH_SEP};
+use util::{fuzzy_score, path_exists, txt_matches};
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Where a match was found relative to the file being completed, nearest first
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+enum Proximity {
+    Local,
+    Module,
+    Crate,
+    Prelude,
+    External,
+}
+
+/// Removes duplicate matches (same filepath, point and name) and sorts the rest, best first.
+///
+/// Exact matches of searchstr come first. After that matches are ordered by where they are
+/// found: locals, the current module, the current crate, the prelude, then std and other crates.
+/// Within each of those better prefix matches come first, then matches are ordered by kind (e.g.
+/// functions before types before modules), then shorter names first.
+pub fn rank_completions(matches: Vec<Match>, searchstr: &str, filepath: &Path,
+                        session: &Session) -> Vec<Match> {
+    let mut seen = HashSet::new();
+    let matches: Vec<Match> = matches.into_iter()
+        .filter(|m| seen.insert((m.filepath.clone(), m.point, m.matchstr.clone())))
+        .collect();
+
+    let crate_dir = crate_dir(filepath);
+    let current_file = canonical(filepath);
+    let prelude = prelude_file().map(|path| session.load_file_and_mask_comments(&path));
+    // most matches share a handful of files, so each is canonicalized once
+    let mut canonicals = HashMap::new();
+
+    let mut ranked: Vec<_> = matches.into_iter().map(|m| {
+        let name = m.matchstr.trim_right_matches('!').to_owned();
+        let matchfile = canonicals.entry(m.filepath.clone())
+            .or_insert_with(|| canonical(&m.filepath)).clone();
+        let proximity = if is_local_binding(m.mtype) {
+            Proximity::Local
+        } else if matchfile == current_file {
+            Proximity::Module
+        } else if crate_dir.as_ref().map_or(false, |dir| matchfile.starts_with(dir)) {
+            Proximity::Crate
+        } else if prelude.as_ref().map_or(false, |p| txt_matches(SearchType::ExactMatch, &name, p)) {
+            Proximity::Prelude
+        } else {
+            Proximity::External
+        };
+        // lower is better for all of these, so the key can be compared directly
+        let key = (name != searchstr,
+                   proximity,
+                   !name.starts_with(searchstr),
+                   usize::max_value() - fuzzy_score(searchstr, &name).unwrap_or(0),
+                   mtype_rank(m.mtype),
+                   name.len());
+        (key, m)
+    }).collect();
+
+    // stable, so matches that rank the same stay in scope order
+    ranked.sort_by(|a, b| a.0.cmp(&b.0));
+    ranked.into_iter().map(|(_, m)| m).collect()
+}
+
+fn is_local_binding(mtype: MatchType) -> bool {
+    match mtype {
+        MatchType::Let | MatchType::IfLet | MatchType::WhileLet |
+        MatchType::For | MatchType::FnArg | MatchType::MatchArm => true,
+        _ => false
+    }
+}
+
+fn mtype_rank(mtype: MatchType) -> usize {
+    match mtype {
+        MatchType::Let | MatchType::IfLet | MatchType::WhileLet | MatchType::For |
+        MatchType::FnArg | MatchType::MatchArm | MatchType::StructField => 0,
+        MatchType::Function => 1,
+        MatchType::Const | MatchType::Static => 2,
+        MatchType::Struct | MatchType::Enum | MatchType::EnumVariant |
+        MatchType::Type | MatchType::TypeParameter | MatchType::Trait => 3,
+        MatchType::Macro => 4,
+        MatchType::Module | MatchType::Crate => 5,
+        MatchType::Impl | MatchType::Builtin => 6,
+    }
+}
+
+// paths are compared canonicalized, since the same file can be reached as ./foo.rs and foo.rs
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or(path.to_path_buf())
+}
+
+/// The directory containing the crate root of filepath
+fn crate_dir(filepath: &Path) -> Option<PathBuf> {
+    let dir = filepath.parent().unwrap_or(Path::new(""));
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    find_possible_crate_root_modules(dir).into_iter().nth(0)
+        .and_then(|root| root.parent().map(canonical))
+}
+
+fn prelude_file() -> Option<PathBuf> {
+    let srcpaths = match env::var("RUST_SRC_PATH") {
+        Ok(paths) => paths,
+        Err(_) => return None
+    };
+    srcpaths.split(PATH_SEP)
+        .map(|srcpath| Path::new(srcpath).join("libstd").join("prelude").join("v1.rs"))
+        .find(|path| path_exists(path))
+}
+
+#[test]
+fn ranks_locals_and_functions_before_other_kinds() {
+    assert!(mtype_rank(MatchType::Let) < mtype_rank(MatchType::Function));
+    assert!(mtype_rank(MatchType::Function) < mtype_rank(MatchType::Struct));
+    assert!(mtype_rank(MatchType::Struct) < mtype_rank(MatchType::Module));
+    assert!(Proximity::Local < Proximity::Module && Proximity::Prelude < Proximity::External);
+}
@@ -0,0 +1,116 @@
+// Find references
+//
+// Works backwards from find_definition: every occurrence of the identifier in the crate's source
+// files is resolved with find_definition, and kept if it lands on the same definition.
+
+use core::{self, Match, MatchType, Session};
+use util::{find_ident_occurrences, same_file};
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ReferenceKind {
+    Definition,
+    Read,
+    Write,
+}
+
+#[derive(Debug,Clone)]
+pub struct Reference {
+    pub filepath: PathBuf,
+    pub point: usize,          // start of the identifier
+    pub kind: ReferenceKind,
+}
+
+/// Finds all the references in the current crate to the item whose name is at pos.
+///
+/// Returns the definition of the item along with the references (including the definition
+/// itself), ordered by file and position.
+pub fn find_references(src: &str, filepath: &Path, pos: usize,
+                       session: &Session) -> Option<(Match, Vec<Reference>)> {
+    let def = match core::find_definition(src, filepath, pos, session) {
+        Some(def) => def,
+        None => return None
+    };
+    debug!("find_references: definition is {:?}", def);
+
+    let mut files = if is_local_variable(def.mtype) {
+        // locals can't be referred to from anywhere else
+        vec![def.filepath.clone()]
+    } else {
+        crate_files(filepath, session)
+    };
+    for path in &[filepath, &def.filepath] {
+        if !files.iter().any(|f| same_file(f, path)) {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    let mut out = Vec::new();
+    for file in &files {
+        let fsrc = session.load_file_and_mask_comments(file);
+        for point in find_ident_occurrences(&fsrc, &def.matchstr) {
+            let end = point + def.matchstr.len();
+            let kind = if point == def.point && same_file(file, &def.filepath) {
+                ReferenceKind::Definition
+            } else {
+                match core::find_definition(&fsrc, file, end, session) {
+                    Some(ref m) if m.point == def.point && same_file(&m.filepath, &def.filepath) =>
+                        reference_kind(&fsrc, point, end),
+                    _ => continue
+                }
+            };
+            out.push(Reference { filepath: file.clone(), point: point, kind: kind });
+        }
+    }
+    out.sort_by(|a, b| (&a.filepath, a.point).cmp(&(&b.filepath, b.point)));
+    Some((def, out))
+}
+
+fn is_local_variable(mtype: MatchType) -> bool {
+    match mtype {
+        MatchType::Let | MatchType::IfLet | MatchType::WhileLet |
+        MatchType::For | MatchType::FnArg | MatchType::MatchArm => true,
+        _ => false
+    }
+}
+
+/// The source files of the crate that filepath is in: the files of the modules reachable from
+/// the crate root through `mod` declarations, plus filepath itself even if no module declares it.
+///
+/// Crates under RUST_SRC_PATH aren't indexed (see impls::build_impl_index), so for those it's
+/// just filepath.
+pub fn crate_files(filepath: &Path, session: &Session) -> Vec<PathBuf> {
+    let mut out = session.impl_index(filepath).files().to_vec();
+    if !out.iter().any(|f| same_file(f, filepath)) {
+        out.push(filepath.to_path_buf());
+    }
+    out.sort();
+    out
+}
+
+/// Guesses whether the identifier between start and end is assigned to
+fn reference_kind(src: &str, start: usize, end: usize) -> ReferenceKind {
+    let after = src[end..].trim_left();
+    let assigned =
+        (after.starts_with('=') && !after.starts_with("==") && !after.starts_with("=>")) ||
+        ["+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=", "<<=", ">>="].iter()
+            .any(|op| after.starts_with(op));
+    let borrowed_mut = src[..start].trim_right().ends_with("&mut");
+
+    if assigned || borrowed_mut {
+        ReferenceKind::Write
+    } else {
+        ReferenceKind::Read
+    }
+}
+
+#[test]
+fn classifies_assignments_as_writes() {
+    let src = "a = 1; a == 2; a += 3; foo(&mut a); b = a;";
+    assert_eq!(ReferenceKind::Write, reference_kind(src, 0, 1));
+    assert_eq!(ReferenceKind::Read, reference_kind(src, 7, 8));
+    assert_eq!(ReferenceKind::Write, reference_kind(src, 15, 16));
+    assert_eq!(ReferenceKind::Write, reference_kind(src, 32, 33));
+    assert_eq!(ReferenceKind::Read, reference_kind(src, 40, 41));
+}
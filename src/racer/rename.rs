@@ -0,0 +1,187 @@
+// Rename
+//
+// Builds on find_references: every reference to the definition is replaced with the new name.
+// Nothing is written, the edits are returned for the editor to apply.
+
+use core::{self, Match, MatchType, Namespace, PathSegment, SearchType, Session};
+use matchers::first_line;
+use nameres;
+use outline::{self, OutlineItem};
+use references::{self, Reference};
+use scopes;
+use util::{self, find_ident_occurrences, is_ident_char};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct Edit {
+    pub filepath: PathBuf,
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+#[derive(Debug,Clone)]
+pub enum RenameError {
+    NoDefinition,
+    InvalidName(String),
+    NotInCrate(Match),          // defined in std or a dependency
+    FileModule(Match),          // the module's file would need renaming
+    Collision(Match),           // the existing item with the new name
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenameError::NoDefinition =>
+                write!(f, "no definition found for the name at the point"),
+            RenameError::InvalidName(ref name) =>
+                write!(f, "`{}` is not a valid identifier", name),
+            RenameError::NotInCrate(ref m) =>
+                write!(f, "`{}` is defined outside the crate, in {}", m.matchstr, m.filepath.display()),
+            RenameError::FileModule(ref m) =>
+                write!(f, "module `{}` is declared in its own file, {}", m.matchstr, m.filepath.display()),
+            RenameError::Collision(ref m) =>
+                write!(f, "`{}` is already defined in {}: {}", m.matchstr, m.filepath.display(),
+                       m.contextstr.trim()),
+        }
+    }
+}
+
+/// Computes the edits that rename the item whose name is at pos to newname, across the crate.
+///
+/// If the name at pos is a `use ... as` alias, only the alias is renamed. Refuses if newname
+/// would collide with an existing name in scope.
+pub fn rename(src: &str, filepath: &Path, pos: usize, newname: &str,
+              session: &Session) -> Result<Vec<Edit>, RenameError> {
+    if !is_valid_ident(newname) {
+        return Err(RenameError::InvalidName(newname.to_owned()));
+    }
+
+    let (def, refs) = match references::find_references(src, filepath, pos, session) {
+        Some(res) => res,
+        None => return Err(RenameError::NoDefinition)
+    };
+    if def.mtype == MatchType::Module && def.point == 0 {
+        return Err(RenameError::FileModule(def));
+    }
+    let crate_files = references::crate_files(filepath, session);
+    if !crate_files.iter().any(|f| util::same_file(f, &def.filepath)) {
+        return Err(RenameError::NotInCrate(def));
+    }
+
+    let (start, _) = util::expand_ident(src, pos);
+    let name = &src[start..util::find_ident_end(src, pos)];
+    // where the name being renamed is declared
+    let (oldname, declpath, declpoint, refs) = if name != def.matchstr {
+        // an alias, which can only be used in the file that declares it
+        let refs = alias_references(src, filepath, name, &def, session);
+        let declpoint = refs.iter().map(|r| r.point)
+            .find(|&point| src[..point].trim_right().ends_with(" as")).unwrap_or(start);
+        (name.to_owned(), filepath.to_path_buf(), declpoint, refs)
+    } else {
+        (def.matchstr.clone(), def.filepath.clone(), def.point, refs)
+    };
+
+    try!(check_collisions(&def, &declpath, declpoint, &refs, newname, session));
+
+    Ok(refs.into_iter().map(|r| Edit {
+        end: r.point + oldname.len(),
+        filepath: r.filepath,
+        start: r.point,
+        replacement: newname.to_owned(),
+    }).collect())
+}
+
+fn is_valid_ident(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_ident_char) &&
+        !name.starts_with(|c: char| c.is_digit(10)) && !util::KEYWORDS.contains(&name)
+}
+
+/// The occurrences of alias in the file that refer to def, including the alias declaration
+fn alias_references(src: &str, filepath: &Path, alias: &str, def: &Match,
+                    session: &Session) -> Vec<Reference> {
+    let indexed = core::new_source(src.to_owned());
+    let msrc = scopes::mask_comments(indexed.as_ref());
+    find_ident_occurrences(&msrc, alias).into_iter().filter(|&point| {
+        let declaration = msrc[..point].trim_right().ends_with(" as");
+        declaration || core::find_definition(&msrc, filepath, point + alias.len(), session)
+            .map_or(false, |m| m.point == def.point && util::same_file(&m.filepath, &def.filepath))
+    }).map(|point| Reference {
+        filepath: filepath.to_path_buf(),
+        point: point,
+        kind: references::ReferenceKind::Read,
+    }).collect()
+}
+
+// The name is declared at declpoint in declpath: at def, or at the `use ... as` of the alias
+// being renamed, whose siblings are the items of the importing module
+fn check_collisions(def: &Match, declpath: &Path, declpoint: usize, refs: &[Reference],
+                    newname: &str, session: &Session) -> Result<(), RenameError> {
+    let items = outline::outline(declpath, session);
+    let (parent, siblings) = find_siblings(&items, None, declpoint)
+        .unwrap_or_else(|| (None, enclosing_items(&items, declpoint)));
+    if let Some(sibling) = siblings.iter().find(|s| s.name == newname && s.point != declpoint) {
+        let src = session.load_file(declpath);
+        return Err(RenameError::Collision(Match {
+            matchstr: sibling.name.clone(),
+            filepath: declpath.to_path_buf(),
+            point: sibling.point,
+            name_range: (sibling.point, sibling.point + sibling.name.len()),
+            item_range: (sibling.start, sibling.end),
+            local: true,
+            mtype: sibling.mtype,
+            contextstr: first_line(&src[sibling.start..sibling.end]),
+            generic_args: Vec::new(),
+            generic_types: Vec::new(),
+            docs: String::new()
+        }));
+    }
+
+    // fields, variants and methods are only looked up through their parent
+    match parent {
+        Some(MatchType::Struct) | Some(MatchType::Enum) |
+        Some(MatchType::Impl) | Some(MatchType::Trait) => return Ok(()),
+        _ => {}
+    }
+
+    let pathseg = PathSegment { name: newname.to_owned(), types: Vec::new() };
+    for r in refs {
+        let existing = nameres::resolve_name(&pathseg, &r.filepath, r.point, SearchType::ExactMatch,
+                                             Namespace::BothNamespaces, session)
+            .find(|m| !(m.point == def.point && util::same_file(&m.filepath, &def.filepath)));
+        if let Some(m) = existing {
+            return Err(RenameError::Collision(m));
+        }
+    }
+    Ok(())
+}
+
+/// The items alongside the item named at point, and the kind of item they're in
+fn find_siblings<'a>(items: &'a [OutlineItem], parent: Option<MatchType>,
+                     point: usize) -> Option<(Option<MatchType>, &'a [OutlineItem])> {
+    if items.iter().any(|item| item.point == point) {
+        return Some((parent, items));
+    }
+    items.iter()
+        .filter(|item| item.start <= point && point < item.end)
+        .filter_map(|item| find_siblings(&item.children, Some(item.mtype), point))
+        .nth(0)
+}
+
+/// The items in the innermost item around point that has any, e.g. the module a `use` is in
+fn enclosing_items(items: &[OutlineItem], point: usize) -> &[OutlineItem] {
+    items.iter()
+        .find(|item| item.start <= point && point < item.end && !item.children.is_empty())
+        .map_or(items, |item| enclosing_items(&item.children, point))
+}
+
+#[test]
+fn validates_new_names() {
+    assert!(is_valid_ident("foo_bar2"));
+    assert!(!is_valid_ident(""));
+    assert!(!is_valid_ident("2foo"));
+    assert!(!is_valid_ident("foo-bar"));
+    assert!(!is_valid_ident("match"));
+}
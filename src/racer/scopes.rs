@@ -1,5 +1,5 @@
 use {ast, typeinf, util};
-use core::{Src, CompletionType, Session};
+use core::{Src, CompletionType, Encoding, Session};
 #[cfg(test)] use core;
 
 use std::iter::Iterator;
@@ -111,21 +111,178 @@ pub fn split_into_context_and_completion(s: &str) -> (&str, &str, CompletionType
     }
 }
 
+// keywords that can come before a path and a brace without it being a struct literal
+const NOT_STRUCT_LITERAL: &'static [&'static str] = &[
+    "struct", "enum", "union", "trait", "impl", "for", "mod", "type", "if", "while", "match", "in",
+    "->"];
+
+/// If start is where a field name goes in a struct literal or pattern, e.g. `Foo { a: 1, b| }`,
+/// returns the path of the struct and the position of the opening brace.
+///
+/// Only looks at the text, so the path still needs resolving to check that it's a struct.
+pub fn find_struct_literal(msrc: &str, start: usize) -> Option<(&str, usize)> {
+    let before = msrc[..start].trim_right();
+    if !(before.ends_with('{') || before.ends_with(',')) {
+        return None;
+    }
+
+    // find the bracket that start is in
+    let bytes = msrc.as_bytes();
+    let mut depth = 0;
+    let mut brace = None;
+    for i in (0..before.len()).rev() {
+        match bytes[i] {
+            b')' | b']' | b'}' => depth += 1,
+            b'(' | b'[' | b'{' if depth > 0 => depth -= 1,
+            b'{' => {
+                brace = Some(i);
+                break;
+            }
+            // in a call, an array, a block or a match
+            b'(' | b'[' => return None,
+            b';' if depth == 0 => return None,
+            b'>' if depth == 0 && i > 0 && bytes[i - 1] == b'=' => return None,
+            _ => {}
+        }
+    }
+    let brace = match brace {
+        Some(brace) => brace,
+        None => return None
+    };
+
+    let head = msrc[..brace].trim_right();
+    let pathstart = head.rfind(|c: char| !(util::is_ident_char(c) || c == ':')).map_or(0, |i| i + 1);
+    let path = &head[pathstart..];
+    let is_type_name = path.rsplit("::").next()
+        .and_then(|name| name.chars().next())
+        .map_or(false, char::is_uppercase);
+    let keyword = head[..pathstart].split_whitespace().last().unwrap_or("");
+    if !is_type_name || path.starts_with(':') || NOT_STRUCT_LITERAL.contains(&keyword) {
+        return None;
+    }
+    Some((path, brace))
+}
+
+/// The fields given in the struct literal whose opening brace is at brace, as (point, name)
+pub fn struct_literal_fields(msrc: &str, brace: usize) -> Vec<(usize, &str)> {
+    let mut out = Vec::new();
+    let mut i = brace + 1;
+    while i < msrc.len() {
+        let start = i + (msrc[i..].len() - msrc[i..].trim_left().len());
+        let end = end_of_list_item(msrc, start);
+        let nameend = msrc[start..end].find(|c: char| !util::is_ident_char(c)).map_or(end, |n| start + n);
+        if nameend > start {
+            out.push((start, &msrc[start..nameend]));
+        }
+        if !msrc[end..].trim_left().starts_with(',') {
+            break;
+        }
+        i = end + (msrc[end..].len() - msrc[end..].trim_left().len()) + 1;
+    }
+    out
+}
+
+#[test]
+fn finds_struct_literals_and_their_fields() {
+    let src = "let a = Foo { x: bar(1, 2), y, ..Default::default() };";
+    let brace = src.find('{').unwrap();
+    assert_eq!(Some(("Foo", brace)), find_struct_literal(src, src.find("y,").unwrap()));
+    assert_eq!(vec![(brace + 2, "x"), (brace + 16, "y")], struct_literal_fields(src, brace));
+    assert_eq!(None, find_struct_literal(src, src.find("2)").unwrap()));
+    assert_eq!(None, find_struct_literal("impl Foo { f", 11));
+    assert_eq!(None, find_struct_literal("if a == b { f", 12));
+    assert_eq!(None, find_struct_literal("match a { A => 1, f", 18));
+}
+
+// The innermost bracket that is still open at point
+fn find_open_bracket(msrc: &str, point: usize) -> Option<usize> {
+    let bytes = msrc.as_bytes();
+    let mut depth = 0;
+    for i in (0..point).rev() {
+        match bytes[i] {
+            b')' | b']' | b'}' => depth += 1,
+            b'(' | b'[' | b'{' if depth > 0 => depth -= 1,
+            b'(' | b'[' | b'{' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// The `match` keyword of the match whose arms start at brace, if it is one
+fn find_match_keyword(msrc: &str, brace: usize) -> Option<usize> {
+    // the scrutinee can't contain a block or a struct literal, so the keyword is in the text
+    // since the last brace or statement
+    let head = &msrc[..brace];
+    let stmtstart = head.rfind(|c: char| c == ';' || c == '{' || c == '}').map_or(0, |i| i + 1);
+    util::find_ident_occurrences(&head[stmtstart..], "match").pop().map(|i| stmtstart + i)
+}
+
+/// The positions of the `match` keyword and the opening brace of the innermost match whose arms
+/// contain point, or whose `match` keyword is at point
+pub fn find_enclosing_match(msrc: &str, point: usize) -> Option<(usize, usize)> {
+    let (start, _) = util::expand_ident(msrc, point);
+    let end = util::find_ident_end(msrc, point);
+    if &msrc[start..end] == "match" {
+        return msrc[end..].find('{').map(|i| (start, end + i));
+    }
+    let mut point = point;
+    while let Some(open) = find_open_bracket(msrc, point) {
+        if msrc.as_bytes()[open] == b'{' {
+            if let Some(keyword) = find_match_keyword(msrc, open) {
+                return Some((keyword, open));
+            }
+        }
+        point = open;
+    }
+    None
+}
+
+/// If start is where an arm's pattern goes in a match, e.g. `match a { Foo => 1, B| }`, the
+/// positions of the `match` keyword and the opening brace of the arms
+pub fn find_match_arm_pattern(msrc: &str, start: usize) -> Option<(usize, usize)> {
+    let before = msrc[..start].trim_right();
+    if !(before.ends_with('{') || before.ends_with(',') || before.ends_with('}') ||
+         before.ends_with('|')) {
+        return None;
+    }
+    find_open_bracket(msrc, start)
+        .and_then(|open| if msrc.as_bytes()[open] == b'{' { Some(open) } else { None })
+        .and_then(|open| find_match_keyword(msrc, open).map(|keyword| (keyword, open)))
+}
+
+#[test]
+fn finds_match_arm_patterns() {
+    let src = "let a = match foo(b) { Some(x) => { x } Bar | B";
+    let arms = Some((src.find("match").unwrap(), src.find('{').unwrap()));
+    assert_eq!(arms, find_match_arm_pattern(src, src.len() - 1));
+    assert_eq!(arms, find_match_arm_pattern(src, src.find("Bar").unwrap()));
+    assert_eq!(None, find_match_arm_pattern(src, src.find("x)").unwrap()));
+    assert_eq!(arms, find_enclosing_match(src, src.find("x }").unwrap()));
+    assert_eq!(arms, find_enclosing_match(src, 10));
+    assert_eq!(None, find_match_arm_pattern("fn foo() { B", 11));
+}
+
 pub fn get_start_of_search_expr(src: &str, point: usize) -> usize {
+    let bytes = src.as_bytes();
     let mut i = point;
     let mut levels = 0u32;
-    for &b in src.as_bytes()[..point].iter().rev() {
+    while i > 0 {
         i -= 1;
-        match b {
-            b'(' => {
+        match bytes[i] {
+            b'(' | b'[' => {
                 if levels == 0 { return i+1; }
                 levels -= 1;
             },
-            b')' => { levels += 1; },
+            b')' | b']' => { levels += 1; },
+            // a string literal that a method is called on, e.g. "foo".len()
+            b'"' if levels == 0 && bytes.get(i+1) == Some(&b'.') => {
+                return start_of_str_literal(src, i).unwrap_or(i+1);
+            },
             _ => {
                 if levels == 0 &&
-                    !util::is_search_expr_char(char_at(src, i)) ||
-                    util::is_double_dot(src,i) {
+                    (!util::is_search_expr_char(char_at(src, i)) ||
+                     util::is_double_dot(src,i)) {
                     return i+1;
                 }
             }
@@ -134,6 +291,22 @@ pub fn get_start_of_search_expr(src: &str, point: usize) -> usize {
     0
 }
 
+// the position of the opening quote of the string literal whose closing quote is at end
+fn start_of_str_literal(src: &str, end: usize) -> Option<usize> {
+    let bytes = src.as_bytes();
+    let mut i = end;
+    while i > 0 {
+        i -= 1;
+        if bytes[i] == b'"' {
+            let backslashes = bytes[..i].iter().rev().take_while(|&&b| b == b'\\').count();
+            if backslashes % 2 == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
 pub fn get_start_of_pattern(src: &str, point: usize) -> usize {
     let mut i = point-1;
     let mut levels = 0u32;
@@ -200,6 +373,18 @@ fn expand_search_expr_handles_pos_at_end_of_search_str() {
     assert_eq!((0, 7), expand_search_expr("foo.bar", 7))
 }
 
+#[test]
+fn expand_search_expr_handles_indexing() {
+    assert_eq!((4, 17), expand_search_expr("foo(v[bar(1)].baz", 16));
+    assert_eq!((0, 10), expand_search_expr("v[1..].bar", 9))
+}
+
+#[test]
+fn get_start_of_search_expr_includes_a_string_literal() {
+    let src = "let a = \"fo\\\"o\".len";
+    assert_eq!(8, get_start_of_search_expr(src, src.len()));
+}
+
 pub fn mask_comments(src: Src) -> String {
     let mut result = String::with_capacity(src.len());
     let buf_byte = &[b' '; 128];
@@ -270,6 +455,29 @@ pub fn end_of_next_scope(src: &str) -> &str {
     }
 }
 
+/// The end of the comma separated item starting at start, e.g. an enum variant or a struct
+/// field, not including the comma or trailing whitespace
+pub fn end_of_list_item(src: &str, start: usize) -> usize {
+    let bytes = src.as_bytes();
+    let mut depth = 0;
+    let mut end = src.len();
+    for i in start..bytes.len() {
+        match bytes[i] {
+            b'{' | b'(' | b'[' | b'<' => depth += 1,
+            // the arrow of a fn type isn't a closing bracket
+            b'>' if i > 0 && bytes[i - 1] == b'-' => {}
+            b'}' | b')' | b']' | b'>' if depth > 0 => depth -= 1,
+            b'}' | b')' | b']' | b'>' | b',' if depth == 0 => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    start + src[start..end].trim_right().len()
+}
+
+/// Converts a 1-based line and a byte column to a point
 pub fn coords_to_point(src: &str, mut linenum: usize, col: usize) -> usize {
     let mut point = 0;
     for line in src.split('\n') {
@@ -280,6 +488,7 @@ pub fn coords_to_point(src: &str, mut linenum: usize, col: usize) -> usize {
     point + col
 }
 
+/// Converts a point to a 1-based line and a byte column
 pub fn point_to_coords(src: &str, point: usize) -> (usize, usize) {
     let mut linestart = 0;
     let mut nlines = 1;  // lines start at 1
@@ -292,6 +501,42 @@ pub fn point_to_coords(src: &str, point: usize) -> (usize, usize) {
     (nlines, point - linestart)
 }
 
+/// Converts a 1-based line and a column counted in encoding to a point. Columns past the end of
+/// the line are clamped to it, except for bytes where the point is simply offset by the column.
+pub fn encoded_coords_to_point(src: &str, linenum: usize, col: usize, encoding: Encoding) -> usize {
+    let linestart = coords_to_point(src, linenum, 0);
+    if encoding == Encoding::Bytes || linestart > src.len() {
+        return linestart + col;
+    }
+    let mut units = 0;
+    for (i, c) in src[linestart..].char_indices() {
+        if units >= col || c == '\n' {
+            return linestart + i;
+        }
+        units += column_units(c, encoding);
+    }
+    src.len()
+}
+
+/// Converts a point to a 1-based line and a column counted in encoding
+pub fn point_to_encoded_coords(src: &str, point: usize, encoding: Encoding) -> (usize, usize) {
+    let (linenum, col) = point_to_coords(src, point);
+    let linestart = point - col;
+    let col = match encoding {
+        Encoding::Bytes => col,
+        _ => src[linestart..point].chars().fold(0, |n, c| n + column_units(c, encoding))
+    };
+    (linenum, col)
+}
+
+fn column_units(c: char, encoding: Encoding) -> usize {
+    match encoding {
+        Encoding::Bytes => c.len_utf8(),
+        Encoding::Chars => 1,
+        Encoding::Utf16 => c.len_utf16(),
+    }
+}
+
 pub fn point_to_coords_from_file(path: &Path, point: usize, session: &Session) -> Option<(usize, usize)> {
     let mut p = 0;
     for (lineno, line) in session.load_file(path).split('\n').enumerate() {
@@ -304,6 +549,24 @@ pub fn point_to_coords_from_file(path: &Path, point: usize, session: &Session) -
 }
 
 
+#[test]
+fn round_trips_encoded_coords_on_non_ascii_lines() {
+    // é is 2 bytes and 1 utf-16 unit, 𝄞 is 4 bytes and 2 utf-16 units
+    let src = "fn main() {\n    let é𝄞 = \"𝄞é\"; foo();\n}";
+    let point = src.find("foo").unwrap();
+    assert_eq!((2, 27), point_to_encoded_coords(src, point, Encoding::Bytes));
+    assert_eq!((2, 19), point_to_encoded_coords(src, point, Encoding::Chars));
+    assert_eq!((2, 21), point_to_encoded_coords(src, point, Encoding::Utf16));
+    for &encoding in &[Encoding::Bytes, Encoding::Chars, Encoding::Utf16] {
+        for (point, _) in src.char_indices() {
+            let (linenum, col) = point_to_encoded_coords(src, point, encoding);
+            assert_eq!(point, encoded_coords_to_point(src, linenum, col, encoding));
+        }
+    }
+    // past the end of the line
+    assert_eq!(src.find("\n}").unwrap(), encoded_coords_to_point(src, 2, 100, Encoding::Chars));
+}
+
 #[test]
 fn coords_to_point_works() {
     let src = "
@@ -403,3 +666,14 @@ struct foo {
     let s = end_of_next_scope(src);
     assert_eq!(expected, s);
 }
+
+#[test]
+fn finds_end_of_list_items() {
+    let src = "enum E { A(u32, Box<Fn(u8) -> u8>), B { x: HashMap<u8, u8> } , C }";
+    let a = src.find("A(").unwrap();
+    assert_eq!("A(u32, Box<Fn(u8) -> u8>)", &src[a..end_of_list_item(src, a)]);
+    let b = src.find("B {").unwrap();
+    assert_eq!("B { x: HashMap<u8, u8> }", &src[b..end_of_list_item(src, b)]);
+    let c = src.find("C }").unwrap();
+    assert_eq!("C", &src[c..end_of_list_item(src, c)]);
+}
@@ -0,0 +1,174 @@
+// Signature help
+//
+// Given a point inside the parentheses of a call, finds the function being called and which of
+// its arguments the point is in.
+
+use core::{self, MatchType, Session};
+use docs::doc_lines;
+use scopes;
+use snippets::MethodInfo;
+use util::{self, is_ident_char};
+
+use std::path::Path;
+
+#[derive(Debug,Clone)]
+pub struct ArgumentInfo {
+    pub name: String,
+    pub ty: String,     // as written in the declaration, "" for self
+    pub docs: String,   // the line of the function docs that mentions `name`, if any
+}
+
+#[derive(Debug,Clone)]
+pub struct SignatureHelp {
+    pub name: String,
+    pub signature: String,         // the declaration up to the body, on one line
+    pub docs: String,
+    pub args: Vec<ArgumentInfo>,   // excludes self for method calls
+    pub active_arg: usize,
+}
+
+/// Finds the signature of the function whose call encloses pos.
+pub fn find_signature_help(src: &str, filepath: &Path, pos: usize,
+                           session: &Session) -> Option<SignatureHelp> {
+    let indexed = core::new_source(src.to_owned());
+    let msrc = scopes::mask_comments(indexed.as_ref());
+
+    let (active_arg, callee_start, callee_end) = match find_callee(&msrc, pos) {
+        Some(call) => call,
+        None => return None
+    };
+    let is_method_call = msrc[..callee_start].trim_right().ends_with('.');
+
+    let m = match core::find_definition(&msrc, filepath, callee_end, session) {
+        Some(ref m) if m.mtype == MatchType::Function => m.clone(),
+        other => {
+            debug!("find_signature_help: callee is not a function {:?}", other);
+            return None;
+        }
+    };
+
+    let fnsrc = session.load_file(&m.filepath);
+    let fnmsrc = session.load_file_and_mask_comments(&m.filepath);
+    let start = scopes::find_stmt_start(fnmsrc, m.point).unwrap_or(m.point);
+    let decl = function_declaration(&fnsrc[start..]);
+    let info = match MethodInfo::from_source_str(&decl) {
+        Some(info) => info,
+        None => return None
+    };
+
+    let docs = doc_lines(&fnsrc, start);
+    // self is passed implicitly by the method call syntax
+    let has_self = info.args.first().map_or(false, |a| a == "self");
+    let skip = if is_method_call && has_self { 1 } else { 0 };
+    let args = info.args.iter().zip(info.arg_types.iter()).skip(skip).map(|(name, ty)| {
+        ArgumentInfo {
+            name: name.clone(),
+            ty: ty.clone(),
+            docs: arg_docs(&docs, name),
+        }
+    }).collect();
+
+    Some(SignatureHelp {
+        name: m.matchstr.clone(),
+        signature: decl,
+        docs: docs.join("\n"),
+        args: args,
+        active_arg: active_arg,
+    })
+}
+
+/// Finds the active argument and the start and end of the name of the function being called,
+/// skipping out of parens that aren't calls (e.g. tuples and `if (..)`)
+fn find_callee(msrc: &str, pos: usize) -> Option<(usize, usize, usize)> {
+    let mut point = pos;
+    while let Some((paren, active_arg)) = find_enclosing_call(msrc, point) {
+        let callee_end = msrc[..paren].trim_right().len();
+        let (callee_start, _) = util::expand_ident(msrc, callee_end);
+        let callee = &msrc[callee_start..callee_end];
+        // a keyword before the paren (e.g. `if (`) means it isn't a call
+        if !callee.is_empty() && !util::KEYWORDS.contains(&callee) {
+            return Some((active_arg, callee_start, callee_end));
+        }
+        point = paren;
+    }
+    None
+}
+
+/// Finds the open paren of the call enclosing pos, and the index of the argument pos is in
+fn find_enclosing_call(msrc: &str, pos: usize) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut commas = 0;
+    for (i, b) in msrc[..pos].bytes().enumerate().rev() {
+        match b {
+            b')' | b']' | b'}' => depth += 1,
+            b'(' if depth == 0 => return Some((i, commas)),
+            b'[' | b'{' if depth == 0 => return None,
+            b'(' | b'[' | b'{' => depth -= 1,
+            b',' if depth == 0 => commas += 1,
+            b';' if depth == 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The declaration of the function starting at the start of src, with whitespace collapsed
+fn function_declaration(src: &str) -> String {
+    let mut depth = 0i32;
+    let mut end = src.len();
+    for (i, b) in src.bytes().enumerate() {
+        match b {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            // ';' can appear in array types
+            b'{' | b';' if depth == 0 => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+    src[..end].split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The doc line describing the argument, e.g. "* `x` - the x coordinate"
+fn arg_docs(docs: &[String], name: &str) -> String {
+    let quoted = format!("`{}`", name);
+    docs.iter()
+        .find(|line| line.find(&quoted[..]).map_or(false, |i| {
+            // must be the whole name, not just a prefix of something longer
+            !line[i + quoted.len()..].chars().next().map_or(false, is_ident_char)
+        }))
+        .map(|line| line.trim_left_matches(|c| c == '*' || c == '-' || c == ' ').to_owned())
+        .unwrap_or(String::new())
+}
+
+#[test]
+fn finds_active_argument_of_call() {
+    let src = "foo(a, bar(b, c), [d, e], ";
+    assert_eq!(Some((3, 3)), find_enclosing_call(src, src.len()));
+    assert_eq!(Some((10, 1)), find_enclosing_call(src, 14));
+    assert_eq!(None, find_enclosing_call("let a = [1, 2", 13));
+    assert_eq!(None, find_enclosing_call("foo(a); bar", 11));
+}
+
+#[test]
+fn skips_parens_that_are_not_calls() {
+    let src = "foo(a, if (b, c";
+    assert_eq!(Some((1, 0, 3)), find_callee(src, src.len()));
+}
+
+#[test]
+fn reads_declaration_up_to_body() {
+    assert_eq!("fn foo(a: [u8; 4]) -> u32", function_declaration("fn foo(a: [u8; 4])\n    -> u32 { 1 }"));
+    assert_eq!("fn bar(&self)", function_declaration("fn bar(&self);\nfn baz() {}"));
+}
+
+#[test]
+fn extracts_docs_for_arguments() {
+    let src = "/// Adds things\n///\n/// * `a` - the first thing\n/// * `ab` - another\n#[inline]\nfn add(a: u32, ab: u32)";
+    let docs = doc_lines(src, src.find("fn").unwrap());
+    assert_eq!(vec!["Adds things", "", "* `a` - the first thing", "* `ab` - another"], docs);
+    assert_eq!("`a` - the first thing", arg_docs(&docs, "a"));
+    assert_eq!("", arg_docs(&docs, "b"));
+}
@@ -18,14 +18,15 @@ pub fn snippet_for_match(m: &Match, session: &Session) -> String {
     }
 }
 
-struct MethodInfo {
-    name: String,
-    args: Vec<String>
+pub struct MethodInfo {
+    pub name: String,
+    pub args: Vec<String>,
+    pub arg_types: Vec<String>,  // as written in the source, "" if they couldn't be read
 }
 
 impl MethodInfo {
     ///Parses method declaration as string and returns relevant data
-    fn from_source_str(source: &str) -> Option<MethodInfo> {
+    pub fn from_source_str(source: &str) -> Option<MethodInfo> {
         let trim: &[_] = &['\n', '\r', '{', ' '];
         let decorated = format!("{} {{}}()", source.trim_right_matches(trim));
 
@@ -36,19 +37,22 @@ impl MethodInfo {
                     match method.node {
                         ImplItemKind::Method(ref msig, _) => {
                             let decl = &msig.decl;
+                            let codemap = &p.sess.codemap();
+                            let snippet = |span| match codemap.span_to_snippet(span) {
+                                Ok(s) => s,
+                                _ => "".into()
+                            };
                             Some(MethodInfo {
                                 // ident.as_str calls Ident.name.as_str
                                 name: method.ident.name.as_str().to_string(),
                                 args: decl.inputs
                                           .iter()
-                                          .map(|arg| {
-                                              let codemap = &p.sess.codemap();
-                                              match codemap.span_to_snippet(arg.pat.span) {
-                                                  Ok(name) => name,
-                                                  _ => "".into()
-                                              }
-                                          })
-                                          .collect()
+                                          .map(|arg| snippet(arg.pat.span))
+                                          .collect(),
+                                arg_types: decl.inputs
+                                               .iter()
+                                               .map(|arg| snippet(arg.ty.span))
+                                               .collect()
                             })
                         },
                         _ => {
@@ -97,5 +101,11 @@ fn method_info_test() {
     assert_eq!(info.name, "reserve");
     assert_eq!(info.args.len(), 2);
     assert_eq!(info.args[0], "self");
+    assert_eq!(info.arg_types[1], "uint");
     assert_eq!(info.snippet(), "reserve(${1:additional})");
+
+    let info = MethodInfo::from_source_str("fn get(&self, m: HashMap<K, V>, b: [u8; 4])").unwrap();
+    assert_eq!(info.arg_types[0], "");
+    assert_eq!(info.arg_types[1], "HashMap<K, V>");
+    assert_eq!(info.arg_types[2], "[u8; 4]");
 }
@@ -0,0 +1,160 @@
+// Workspace symbols
+//
+// Searches the names of the items in every module reachable from the crate root, following
+// `mod foo;` declarations the same way match_mod does. Dependency crates named by `extern crate`
+// in the crate root can optionally be searched too.
+
+use ast;
+use cargo;
+use core::{Match, MatchType, Session};
+use matchers::first_line;
+use nameres::{find_possible_crate_root_modules, get_module_file};
+use outline::{self, OutlineItem};
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// How well a name matches the query, best first
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+enum MatchQuality {
+    Exact,
+    ExactIgnoringCase,
+    Prefix,
+    PrefixIgnoringCase,
+    Substring,
+}
+
+/// Finds the items whose names contain query (ignoring case) in the crate that filepath
+/// belongs to, and in its dependencies if include_deps is set.
+///
+/// Results are ordered best match first: exact matches, then prefix matches, then the rest,
+/// with the current crate's items before dependencies' and shorter names before longer ones.
+pub fn workspace_symbols(query: &str, filepath: &Path, include_deps: bool,
+                         session: &Session) -> Vec<Match> {
+    let dir = filepath.parent().unwrap_or(Path::new(""));
+    let root = find_possible_crate_root_modules(dir).into_iter().nth(0)
+        .unwrap_or(filepath.to_path_buf());
+    debug!("workspace_symbols: crate root is {:?}", root);
+
+    let mut found = Vec::new();
+    let mut visited = HashSet::new();
+    search_module_file(query, &root, true, session, &mut visited, &mut found);
+
+    if include_deps {
+        for cratefile in dependency_crate_files(&root, session) {
+            search_module_file(query, &cratefile, false, session, &mut visited, &mut found);
+        }
+    }
+
+    found.sort_by(|&(qa, ref a), &(qb, ref b)| {
+        (qa, !a.local, a.matchstr.len(), &a.matchstr, &a.filepath, a.point)
+            .cmp(&(qb, !b.local, b.matchstr.len(), &b.matchstr, &b.filepath, b.point))
+    });
+    found.into_iter().map(|(_, m)| m).collect()
+}
+
+fn match_quality(query: &str, name: &str) -> Option<MatchQuality> {
+    let lquery = query.to_lowercase();
+    let lname = name.to_lowercase();
+    if name == query {
+        Some(MatchQuality::Exact)
+    } else if lname == lquery {
+        Some(MatchQuality::ExactIgnoringCase)
+    } else if name.starts_with(query) {
+        Some(MatchQuality::Prefix)
+    } else if lname.starts_with(&lquery[..]) {
+        Some(MatchQuality::PrefixIgnoringCase)
+    } else if lname.contains(&lquery[..]) {
+        Some(MatchQuality::Substring)
+    } else {
+        None
+    }
+}
+
+fn search_module_file(query: &str, filepath: &Path, local: bool, session: &Session,
+                      visited: &mut HashSet<PathBuf>, out: &mut Vec<(MatchQuality, Match)>) {
+    if !visited.insert(filepath.to_path_buf()) {
+        return;
+    }
+    let items = outline::outline(filepath, session);
+    let searchdir = filepath.parent().unwrap_or(Path::new("")).to_path_buf();
+    search_items(query, &items, filepath, &searchdir, local, session, visited, out);
+}
+
+fn search_items(query: &str, items: &[OutlineItem], filepath: &Path, searchdir: &Path,
+                local: bool, session: &Session,
+                visited: &mut HashSet<PathBuf>, out: &mut Vec<(MatchQuality, Match)>) {
+    let src = session.load_file(filepath);
+    for item in items {
+        // only the public api of dependencies is of interest
+        if !local && !item.public {
+            continue;
+        }
+
+        // impls are named after their self type, which is listed already
+        if item.mtype != MatchType::Impl {
+            if let Some(quality) = match_quality(query, &item.name) {
+                out.push((quality, Match {
+                    matchstr: item.name.clone(),
+                    filepath: filepath.to_path_buf(),
+                    point: item.point,
+                    name_range: (item.point, item.point + item.name.len()),
+                    item_range: (item.start, item.end),
+                    local: local,
+                    mtype: item.mtype,
+                    contextstr: first_line(&src[item.start..item.end]),
+                    generic_args: Vec::new(),
+                    generic_types: Vec::new(),
+                    docs: String::new()
+                }));
+            }
+        }
+
+        if item.mtype == MatchType::Module && !src[item.start..item.end].contains('{') {
+            // mod foo; lives in another file
+            if let Some(modpath) = get_module_file(&item.name, searchdir) {
+                search_module_file(query, &modpath, local, session, visited, out);
+            }
+        } else if item.mtype == MatchType::Module {
+            // files declared in an inline module are in a subdirectory named after it
+            let searchdir = searchdir.join(&item.name);
+            search_items(query, &item.children, filepath, &searchdir, local, session, visited, out);
+        } else if item.mtype != MatchType::Struct {
+            // struct fields are left out, they can't be named on their own
+            search_items(query, &item.children, filepath, searchdir, local, session, visited, out);
+        }
+    }
+}
+
+/// The root files of the crates named by `extern crate` in the crate root that cargo can find
+fn dependency_crate_files(root: &Path, session: &Session) -> Vec<PathBuf> {
+    let msrc = session.load_file_and_mask_comments(root);
+    let src = session.load_file(root);
+    let mut out = Vec::new();
+    for (start, end) in msrc.iter_stmts() {
+        if !msrc[start..end].starts_with("extern crate") {
+            continue;
+        }
+        // unmasked, since the real crate name can be a string literal
+        let extern_crate = ast::parse_extern_crate(src[start..end].to_owned());
+        let name = match extern_crate.realname.or(extern_crate.name) {
+            Some(name) => name,
+            None => continue
+        };
+        match cargo::get_crate_file(&name, root) {
+            Some(cratefile) => out.push(cratefile),
+            None => debug!("workspace_symbols: couldn't find crate {}", name)
+        }
+    }
+    out
+}
+
+#[test]
+fn ranks_exact_then_prefix_then_substring() {
+    assert_eq!(Some(MatchQuality::Exact), match_quality("Foo", "Foo"));
+    assert_eq!(Some(MatchQuality::ExactIgnoringCase), match_quality("foo", "Foo"));
+    assert_eq!(Some(MatchQuality::Prefix), match_quality("Fo", "FooBar"));
+    assert_eq!(Some(MatchQuality::PrefixIgnoringCase), match_quality("foob", "FooBar"));
+    assert_eq!(Some(MatchQuality::Substring), match_quality("bar", "FooBar"));
+    assert_eq!(None, match_quality("baz", "FooBar"));
+}
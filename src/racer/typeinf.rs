@@ -1,14 +1,16 @@
 // Type inference
 
 use core::{Match, Src, Scope, Session};
-use nameres::resolve_path_with_str;
+use nameres::{self, resolve_path_with_str};
 use core::Namespace::TypeNamespace;
 use core;
 use ast;
 use scopes;
 use matchers;
 use core::SearchType::ExactMatch;
-use util::txt_matches;
+use util::{txt_matches, find_ident_occurrences};
+
+use std::path::Path;
 
 fn find_start_of_function_body(src: &str) -> usize {
     // TODO: this should ignore anything inside parens so as to skip the arg list
@@ -44,34 +46,120 @@ fn generates_skeleton_for_mod() {
 
 fn get_type_of_self_arg(m: &Match, msrc: Src, session: &Session) -> Option<core::Ty> {
     debug!("get_type_of_self_arg {:?}", m);
-    scopes::find_impl_start(msrc, m.point, 0).and_then(|start| {
+    get_type_of_self(m.point, &m.filepath, m.local, msrc, session)
+}
+
+/// The type that `Self` refers to at point: the self type of the enclosing impl, or the
+/// enclosing trait
+pub fn get_type_of_self(point: usize, filepath: &Path, local: bool, msrc: Src,
+                        session: &Session) -> Option<core::Ty> {
+    scopes::find_impl_start(msrc, point, 0).and_then(|start| {
         let decl = generate_skeleton_for_parsing(&msrc.from(start));
-        debug!("get_type_of_self_arg impl skeleton |{}|", decl);
+        debug!("get_type_of_self impl skeleton |{}|", decl);
 
         if decl.starts_with("impl") {
             let implres = ast::parse_impl(decl);
-            debug!("get_type_of_self_arg implres |{:?}|", implres);
+            debug!("get_type_of_self implres |{:?}|", implres);
             resolve_path_with_str(&implres.name_path.expect("failed parsing impl name"),
-                                  &m.filepath, start,
+                                  filepath, start,
                                   ExactMatch, TypeNamespace,
                                   session).nth(0).map(core::Ty::TyMatch)
         } else {
             // // must be a trait
             ast::parse_trait(decl).name.and_then(|name| {
+                let namestart = find_ident_occurrences(&msrc[start..], &name).into_iter().nth(0)
+                    .map_or(start, |i| start + i);
+                let name_range = (namestart, namestart + name.len());
+                let end = start + scopes::end_of_next_scope(&msrc[start..]).len();
                 Some(core::Ty::TyMatch(Match {
                            matchstr: name,
-                           filepath: m.filepath.clone(),
+                           filepath: filepath.to_path_buf(),
                            point: start,
-                           local: m.local,
+                           name_range: name_range,
+                           item_range: (start, end),
+                           local: local,
                            mtype: core::MatchType::Trait,
                            contextstr: matchers::first_line(&msrc[start..]),
-                           generic_args: Vec::new(), generic_types: Vec::new()
+                           generic_args: Vec::new(), generic_types: Vec::new(),
+                           docs: String::new()
                 }))
             })
         }
     })
 }
 
+/// The generic parameter named name that is declared by the item at point or by one of the fns,
+/// impls and traits it is in. The match's generic_args are the paths of the traits bounding it.
+pub fn find_type_parameter(name: &str, filepath: &Path, point: usize,
+                           session: &Session) -> Option<Match> {
+    let msrc = session.load_file_and_mask_comments(filepath);
+    let mut point = point;
+    loop {
+        let stmtstart = match scopes::find_stmt_start(msrc, point) {
+            Some(start) => start,
+            None => return None
+        };
+        if let Some(m) = match_type_parameter(name, msrc, stmtstart, filepath) {
+            return Some(m);
+        }
+        let scopestart = scopes::scope_start(msrc, stmtstart);
+        if scopestart == 0 {
+            return None;
+        }
+        // carry on with the item whose body this is
+        point = scopestart - 1;
+    }
+}
+
+fn match_type_parameter(name: &str, msrc: Src, start: usize, filepath: &Path) -> Option<Match> {
+    let blob = &msrc[start..];
+    let header = match blob.find(|c| c == '{' || c == ';') {
+        Some(n) => blob[..n].trim_right(),
+        None => return None
+    };
+    let mut item = header;
+    for prefix in &["pub ", "unsafe "] {
+        if item.starts_with(prefix) {
+            item = item[prefix.len()..].trim_left();
+        }
+    }
+    let decl = if item.starts_with("fn ") {
+        // wrap in "impl blah { }" so that methods get parsed correctly too
+        format!("impl blah {{{}{{}}}}", header)
+    } else if item.starts_with("impl<") || item.starts_with("trait ") {
+        format!("{}{{}}", header)
+    } else {
+        return None;
+    };
+    let generics = ast::parse_generics(decl);
+    let i = match generics.generic_args.iter().position(|arg| arg == name) {
+        Some(i) => i,
+        None => return None
+    };
+    find_ident_occurrences(header, name).into_iter().nth(0).map(|offset| {
+        let point = start + offset;
+        let bounds = generics.generic_bounds[i].clone();
+        let contextstr = if bounds.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}: {}", name, bounds.join(" + "))
+        };
+        Match {
+            matchstr: name.to_owned(),
+            filepath: filepath.to_path_buf(),
+            point: point,
+            name_range: (point, point + name.len()),
+            item_range: (point, scopes::end_of_list_item(&msrc, point)),
+            local: true,
+            mtype: core::MatchType::TypeParameter,
+            contextstr: contextstr,
+            generic_args: bounds,
+            generic_types: Vec::new(),
+            docs: String::new()
+        }
+    })
+}
+
 fn get_type_of_fnarg(m: &Match, msrc: Src, session: &Session) -> Option<core::Ty> {
     if m.matchstr == "self" {
         return get_type_of_self_arg(m, msrc, session);
@@ -157,24 +245,36 @@ fn get_type_of_for_expr(m: &Match, msrc: Src, session: &Session) -> Option<core:
     }
 }
 
-pub fn get_struct_field_type(fieldname: &str, structmatch: &Match, session: &Session) -> Option<core::Ty> {
-    assert!(structmatch.mtype == core::MatchType::Struct);
-
+/// The fields of a struct, or of an enum struct variant, as (name, point, type)
+pub fn get_struct_fields(structmatch: &Match, session: &Session) -> Vec<(String, usize, Option<core::Ty>)> {
     let src = session.load_file(&structmatch.filepath);
 
-    let opoint = scopes::find_stmt_start(src, structmatch.point);
-    let structsrc = scopes::end_of_next_scope(&src[opoint.unwrap()..]);
-
-    let fields = ast::parse_struct_fields(structsrc.to_owned(), Scope::from_match(structmatch));
-    for (field, _, ty) in fields.into_iter() {
-        if fieldname == field {
-            return ty;
+    let (structsrc, offset) = if let core::MatchType::EnumVariant = structmatch.mtype {
+        // decorate the enum variant src to make it look like a struct
+        let variantsrc = &src[structmatch.point..scopes::end_of_list_item(&src, structmatch.point)];
+        if !variantsrc.contains('{') {
+            return Vec::new();
         }
-    }
-    None
+        ("struct ".to_owned() + variantsrc, structmatch.point - "struct ".len())
+    } else {
+        assert!(structmatch.mtype == core::MatchType::Struct);
+        let opoint = scopes::find_stmt_start(src, structmatch.point).unwrap();
+        (scopes::end_of_next_scope(&src[opoint..]).to_owned(), opoint)
+    };
+
+    ast::parse_struct_fields(structsrc, Scope::from_match(structmatch)).into_iter()
+        .map(|(field, fpos, ty)| (field, offset + fpos, ty))
+        .collect()
 }
 
-pub fn get_tuplestruct_field_type(fieldnum: u32, structmatch: &Match, session: &Session) -> Option<core::Ty> {
+pub fn get_struct_field_type(fieldname: &str, structmatch: &Match, session: &Session) -> Option<core::Ty> {
+    get_struct_fields(structmatch, session).into_iter()
+        .find(|&(ref field, _, _)| field == fieldname)
+        .and_then(|(_, _, ty)| ty)
+}
+
+/// The types of the fields of a tuple struct, or of an enum tuple variant
+pub fn get_tuplestruct_fields(structmatch: &Match, session: &Session) -> Vec<Option<core::Ty>> {
     let src = session.load_file(&structmatch.filepath);
 
     let structsrc = if let core::MatchType::EnumVariant = structmatch.mtype {
@@ -189,17 +289,16 @@ pub fn get_tuplestruct_field_type(fieldnum: u32, structmatch: &Match, session: &
         (*get_first_stmt(src.from(opoint.unwrap()))).to_owned()
     };
 
-    debug!("get_tuplestruct_field_type structsrc=|{}|", structsrc);
+    debug!("get_tuplestruct_fields structsrc=|{}|", structsrc);
 
-    let fields = ast::parse_struct_fields(structsrc, Scope::from_match(structmatch));
-    let mut i = 0u32;
-    for (_, _, ty) in fields.into_iter() {
-        if i == fieldnum {
-            return ty;
-        }
-        i+=1;
-    }
-    None
+    ast::parse_struct_fields(structsrc, Scope::from_match(structmatch)).into_iter()
+        .map(|(_, _, ty)| ty)
+        .collect()
+}
+
+pub fn get_tuplestruct_field_type(fieldnum: u32, structmatch: &Match, session: &Session) -> Option<core::Ty> {
+    get_tuplestruct_fields(structmatch, session).into_iter().nth(fieldnum as usize)
+        .and_then(|ty| ty)
 }
 
 pub fn get_first_stmt(src: Src) -> Src {
@@ -273,13 +372,107 @@ pub fn get_function_declaration(fnmatch: &Match, session: &Session) -> String {
 pub fn get_return_type_of_function(fnmatch: &Match, session: &Session) -> Option<core::Ty> {
     let src = session.load_file(&fnmatch.filepath);
     let point = scopes::find_stmt_start(src, fnmatch.point).unwrap();
-    (&src[point..]).find("{").and_then(|n| {
+    // a trait's methods can end in `;` rather than a body
+    (&src[point..]).find(|c| c == '{' || c == ';').and_then(|n| {
         // wrap in "impl blah { }" so that methods get parsed correctly too
         let mut decl = String::new();
         decl.push_str("impl blah {");
-        decl.push_str(&src[point..(point+n+1)]);
-        decl.push_str("}}");
+        decl.push_str(&src[point..(point+n)]);
+        decl.push_str("{}}");
         debug!("get_return_type_of_function: passing in |{}|", decl);
         ast::parse_fn_output(decl, Scope::from_match(fnmatch))
     })
 }
+
+/// The type given to the associated type name by the impl block starting at implstart, e.g.
+/// `Point` for `type Item = Point;`
+pub fn get_assoc_type_in_impl(msrc: Src, implstart: usize, filepath: &Path,
+                              name: &str) -> Option<core::PathSearch> {
+    let bodystart = match msrc[implstart..].find('{') {
+        Some(n) => implstart + n + 1,
+        None => return None
+    };
+    let body = msrc.from(bodystart);
+    for (start, end) in body.iter_stmts() {
+        let blob = &body[start..end];
+        if !blob.starts_with("type ") {
+            continue;
+        }
+        let typedef = ast::parse_type(blob.to_owned());
+        if typedef.name.as_ref().map_or(false, |n| n == name) {
+            return typedef.type_.map(|path| core::PathSearch {
+                path: path,
+                filepath: filepath.to_path_buf(),
+                point: bodystart + start
+            });
+        }
+    }
+    None
+}
+
+#[test]
+fn finds_assoc_types_in_impls() {
+    let src = core::new_source("impl Iterator for Counter {\n    type Item = Point;\n    fn next(&mut self) {}\n}".to_owned());
+    let item = get_assoc_type_in_impl(src.as_ref(), 0, Path::new("lib.rs"), "Item").unwrap();
+    assert_eq!("Point", item.path.to_string());
+    assert_eq!(src.find("type").unwrap(), item.point);
+    assert!(get_assoc_type_in_impl(src.as_ref(), 0, Path::new("lib.rs"), "Output").is_none());
+}
+
+/// The type given to the associated type name by one of the impls of selfm's type
+pub fn get_assoc_type_of_impls(selfm: &Match, name: &str, session: &Session) -> Option<core::PathSearch> {
+    nameres::search_for_impls(selfm.point, &selfm.matchstr, &selfm.filepath, selfm.local, false, session)
+        .filter_map(|implm| {
+            let msrc = session.load_file_and_mask_comments(&implm.filepath);
+            get_assoc_type_in_impl(msrc, implm.item_range.0, &implm.filepath, name)
+        }).nth(0)
+}
+
+/// The `Output` of the type's `Index` impl for indexing with a range (e.g. `v[1..]`) if range
+/// is set, otherwise with anything else. Outputs that aren't paths, like `[T]`, aren't found.
+pub fn get_index_output_of_impls(selfm: &Match, range: bool, session: &Session) -> Option<core::PathSearch> {
+    for implm in nameres::search_for_impls(selfm.point, &selfm.matchstr, &selfm.filepath,
+                                           selfm.local, false, session) {
+        let msrc = session.load_file_and_mask_comments(&implm.filepath);
+        let implstart = implm.item_range.0;
+        let decl = match msrc[implstart..].find('{') {
+            Some(n) => format!("{}}}", &msrc[implstart..implstart + n + 1]),
+            None => continue
+        };
+        let index_is_range = match ast::parse_impl(decl).trait_path {
+            Some(ref traitpath) => match traitpath.segments.last() {
+                Some(seg) if seg.name == "Index" => seg.types.first().map_or(false, |idx| {
+                    idx.segments.last().map_or(false, |idxseg| idxseg.name.starts_with("Range"))
+                }),
+                _ => continue
+            },
+            None => continue
+        };
+        if index_is_range != range {
+            continue;
+        }
+        if let Some(output) = get_assoc_type_in_impl(msrc, implstart, &implm.filepath, "Output") {
+            return Some(output);
+        }
+    }
+    None
+}
+
+/// The traits of a trait object type. Each carries the associated type bindings like a struct
+/// carries its generic types: the names in generic_args and the types in generic_types.
+pub fn get_traits_of_trait_object(paths: &[core::Path], bindings: &[(String, core::Path)],
+                                  scope: &Scope, session: &Session) -> Vec<Match> {
+    paths.iter().filter_map(|path| {
+        resolve_path_with_str(path, &scope.filepath, scope.point, ExactMatch, TypeNamespace, session)
+            .find(|m| m.mtype == core::MatchType::Trait)
+    }).map(|m| {
+        let types = bindings.iter().map(|&(_, ref path)| core::PathSearch {
+            path: path.clone(),
+            filepath: scope.filepath.clone(),
+            point: scope.point
+        }).collect();
+        let mut m = m.with_generic_types(types);
+        m.generic_args = bindings.iter().map(|&(ref name, _)| name.clone()).collect();
+        m
+    }).collect()
+}
@@ -1,12 +1,20 @@
 // Small functions of utility
 
-use core::SearchType::{self, ExactMatch, StartsWith};
+use core::SearchType::{self, ExactMatch, StartsWith, Fuzzy};
 use core::Session;
 use std;
 use std::cmp;
 use std::fs::File;
 use std::path::Path;
 
+// the strict and reserved keywords, which can't be used as names
+pub const KEYWORDS: &'static [&'static str] = &[
+    "abstract", "alignof", "as", "become", "box", "break", "const", "continue", "crate", "do",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "offsetof", "override", "priv", "proc", "pub", "pure",
+    "ref", "return", "Self", "self", "sizeof", "static", "struct", "super", "trait", "true",
+    "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield"];
+
 pub fn getline(filepath: &Path, linenum: usize, session: &Session) -> String {
     let src = session.load_file(filepath);
     src.src.code.lines().nth(linenum - 1).unwrap_or("not found").to_string()
@@ -61,6 +69,14 @@ pub fn txt_matches(stype: SearchType, needle: &str, haystack: &str) -> bool {
                 n += 1;
             }
             false
+        },
+        Fuzzy => {
+            // needle is a single identifier here, patterns like "fn foo" can't be fuzzy
+            if needle.is_empty() {
+                return true;
+            }
+            haystack.split(|c: char| !is_ident_char(c))
+                .any(|word| fuzzy_matches(needle, word))
         }
     }
 }
@@ -68,10 +84,92 @@ pub fn txt_matches(stype: SearchType, needle: &str, haystack: &str) -> bool {
 pub fn symbol_matches(stype: SearchType, searchstr: &str, candidate: &str) -> bool {
    match stype {
         ExactMatch => searchstr == candidate,
-        StartsWith => candidate.starts_with(searchstr)
+        StartsWith => candidate.starts_with(searchstr),
+        Fuzzy => fuzzy_matches(searchstr, candidate)
     }
 }
 
+/// Whether searchstr fuzzy matches candidate, i.e. whether fuzzy_score would score it, without
+/// scoring. Cheap enough to run on every identifier searched; only the results get scored.
+pub fn fuzzy_matches(searchstr: &str, candidate: &str) -> bool {
+    let mut search = searchstr.chars();
+    let first = match search.next() {
+        Some(first) => first,
+        None => return true
+    };
+    // the earliest hump matching the first char leaves the most room for the rest
+    let mut prev = None;
+    let mut rest = None;
+    for (i, c) in candidate.char_indices() {
+        let hump = prev.map_or(true, |p: char| (p == '_' && c != '_') ||
+                                               (c.is_uppercase() && !p.is_uppercase()));
+        if hump && chars_match(first, c) {
+            rest = Some(&candidate[i + c.len_utf8()..]);
+            break;
+        }
+        prev = Some(c);
+    }
+    match rest {
+        Some(rest) => {
+            let mut cand = rest.chars();
+            search.all(|s| cand.any(|c| chars_match(s, c)))
+        }
+        None => false
+    }
+}
+
+fn chars_match(s: char, c: char) -> bool {
+    s == c || s.to_lowercase().eq(c.to_lowercase())
+}
+
+// bonuses for a fuzzy matched char
+const FUZZY_MATCH: usize = 1;
+const FUZZY_SAME_CASE: usize = 1;
+const FUZZY_CONSECUTIVE: usize = 10;
+const FUZZY_HUMP: usize = 10;
+
+/// Scores how well searchstr matches candidate as a case insensitive subsequence, higher is
+/// better. The first char must match the start of a camel or snake case hump, e.g. `hmap`
+/// matches `HashMap` and `rts` matches `read_to_string`, but `ap` doesn't match `HashMap`.
+/// Returns None if it doesn't match at all.
+pub fn fuzzy_score(searchstr: &str, candidate: &str) -> Option<usize> {
+    let search: Vec<char> = searchstr.chars().collect();
+    let cand: Vec<char> = candidate.chars().collect();
+    if search.is_empty() {
+        return Some(0);
+    }
+
+    // best[j] is the best score for the search chars so far, with the last one matched at j
+    let mut best: Vec<Option<usize>> = Vec::new();
+    for (i, &s) in search.iter().enumerate() {
+        let mut next = vec![None; cand.len()];
+        for (j, &c) in cand.iter().enumerate() {
+            if !chars_match(s, c) {
+                continue;
+            }
+            let hump = is_hump_start(&cand, j);
+            let mut score = FUZZY_MATCH;
+            if s == c { score += FUZZY_SAME_CASE; }
+            if hump { score += FUZZY_HUMP; }
+
+            next[j] = if i == 0 {
+                if hump { Some(score) } else { None }
+            } else {
+                (0..j).filter_map(|k| best[k].map(|prev| {
+                    prev + score + if k + 1 == j { FUZZY_CONSECUTIVE } else { 0 }
+                })).max()
+            };
+        }
+        best = next;
+    }
+    best.into_iter().filter_map(|score| score).max()
+}
+
+fn is_hump_start(cand: &[char], j: usize) -> bool {
+    j == 0 || (cand[j - 1] == '_' && cand[j] != '_') ||
+        (cand[j].is_uppercase() && !cand[j - 1].is_uppercase())
+}
+
 // pub fn get_backtrace() -> String {
 //     let mut m = std::old_io::MemWriter::new();
 //     let s = std::rt::backtrace::write(&mut m)
@@ -92,6 +190,28 @@ fn txt_matches_matches_stuff() {
     assert_eq!(true, txt_matches(StartsWith, "Vec","use Vector"));
     assert_eq!(false, txt_matches(StartsWith, "Vec","use aVector"));
     assert_eq!(true, txt_matches(ExactMatch, "Vec","use Vec"));
+    assert_eq!(true, txt_matches(Fuzzy, "vc","use Vector"));
+    assert_eq!(false, txt_matches(Fuzzy, "ec","use Vector"));
+}
+
+#[test]
+fn fuzzy_matches_subsequences_from_a_hump() {
+    assert!(symbol_matches(Fuzzy, "hmap", "HashMap"));
+    assert!(symbol_matches(Fuzzy, "rts", "read_to_string"));
+    assert!(symbol_matches(Fuzzy, "map", "BTreeMap"));
+    assert!(!symbol_matches(Fuzzy, "ap", "HashMap"));
+    assert!(!symbol_matches(Fuzzy, "hmapp", "HashMap"));
+    for &(search, cand) in &[("hmap", "HashMap"), ("ap", "HashMap"), ("rts", "read_to_string"),
+                             ("hmapp", "HashMap"), ("tm", "BTreeMap"), ("", "Vec")] {
+        assert_eq!(fuzzy_score(search, cand).is_some(), fuzzy_matches(search, cand));
+    }
+
+    // prefixes beat humps, which beat scattered chars
+    let prefix = fuzzy_score("has", "HashMap").unwrap();
+    let humps = fuzzy_score("hm", "HashMap").unwrap();
+    let scattered = fuzzy_score("hs", "HashMap").unwrap();
+    assert!(prefix > humps && humps > scattered);
+    assert!(fuzzy_score("rts", "read_to_string") > fuzzy_score("rts", "restrictions"));
 }
 
 
@@ -137,6 +257,28 @@ fn find_ident_end_unicode() {
     assert_eq!(10, find_ident_end("ends_in_µ", 0));
 }
 
+/// Start points of whole-word occurrences of name in src
+pub fn find_ident_occurrences(src: &str, name: &str) -> Vec<usize> {
+    let mut out = Vec::new();
+    let mut from = 0;
+    while let Some(i) = src[from..].find(name) {
+        let start = from + i;
+        let end = start + name.len();
+        let before = src[..start].chars().rev().next();
+        let after = src[end..].chars().next();
+        if !before.map_or(false, is_ident_char) && !after.map_or(false, is_ident_char) {
+            out.push(start);
+        }
+        from = end;
+    }
+    out
+}
+
+#[test]
+fn finds_whole_word_occurrences() {
+    assert_eq!(vec![0, 18], find_ident_occurrences("foo foobar barfoo foo", "foo"));
+}
+
 // PD: short term replacement for .char_at() function. Should be replaced once
 // that stabilizes
 pub fn char_at(src: &str, i: usize) -> char {
@@ -149,6 +291,17 @@ pub fn path_exists<P: AsRef<Path>>(path: P) -> bool {
     is_dir(&path) || File::open(path).is_ok()
 }
 
+/// Whether a and b are the same file, e.g. ./foo.rs and foo.rs
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false
+    }
+}
+
 // PD: short term replacement for path.is_dir() (PathExt trait). Replace once
 // that stabilizes
 pub fn is_dir<P: AsRef<Path>>(path: P) -> bool {
@@ -5,6 +5,7 @@ extern crate rand;
 use racer::core::complete_from_file;
 use racer::core::find_definition;
 use racer::core;
+use racer::docs;
 use racer::scopes;
 use racer::util;
 
@@ -1635,3 +1636,1227 @@ fn completes_methods_on_deref_generic_type() {
                                     .nth(0).expect("No match found").matchstr;
     assert_eq!(got_str, "one".to_string());
 }
+
+#[test]
+fn lsp_server_completes_and_finds_definition_in_open_buffer() {
+    use racer::lsp;
+    use std::io::Cursor;
+
+    fn frame(body: String) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    let src = "fn apple() {}\nfn main() {\n    apple();\n    let b = ap\n}\n";
+    let f = TmpFile::new("");
+    let uri = lsp::path_to_uri(&env::current_dir().unwrap().join(f.path()));
+    let text = src.replace("\n", "\\n");
+
+    let mut input = String::new();
+    input.push_str(&frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_owned()));
+    input.push_str(&frame(format!(r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{}","languageId":"rust","version":1,"text":"{}"}}}}}}"#, uri, text)));
+    input.push_str(&frame(format!(r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/completion","params":{{"textDocument":{{"uri":"{}"}},"position":{{"line":3,"character":14}}}}}}"#, uri)));
+    input.push_str(&frame(format!(r#"{{"jsonrpc":"2.0","id":3,"method":"textDocument/definition","params":{{"textDocument":{{"uri":"{}"}},"position":{{"line":2,"character":6}}}}}}"#, uri)));
+    input.push_str(&frame(r#"{"jsonrpc":"2.0","method":"exit"}"#.to_owned()));
+
+    let mut output = Vec::new();
+    lsp::run(&mut Cursor::new(input.into_bytes()), &mut output).unwrap();
+
+    let mut output = Cursor::new(output);
+    let init = lsp::read_message(&mut output).unwrap().unwrap();
+    assert!(init.find_path(&["result", "capabilities", "completionProvider"]).is_some());
+
+    let completion = lsp::read_message(&mut output).unwrap().unwrap();
+    let items = completion.find("result").unwrap().as_array().unwrap();
+    assert_eq!(Some("apple"), items[0].find("label").unwrap().as_string());
+
+    let definition = lsp::read_message(&mut output).unwrap().unwrap();
+    let start = definition.find_path(&["result", "range", "start"]).unwrap();
+    assert_eq!(Some(0), start.find("line").unwrap().as_u64());
+    assert_eq!(Some(3), start.find("character").unwrap().as_u64());
+}
+
+#[test]
+fn file_cache_reloads_files_changed_on_disk() {
+    let f = TmpFile::new("fn apple() {}\n");
+    let cache = core::FileCache::new();
+    {
+        let session = core::Session::from_path(&cache, f.path(), f.path());
+        assert_eq!("fn apple() {}\n", &*session.load_file(f.path()));
+    }
+
+    File::create(f.path()).unwrap().write_all(b"fn banana() {}\n").unwrap();
+
+    // still served from the cache until invalidated
+    {
+        let session = core::Session::from_path(&cache, f.path(), f.path());
+        assert_eq!("fn apple() {}\n", &*session.load_file(f.path()));
+    }
+
+    cache.invalidate_stale_files();
+    let session = core::Session::from_path(&cache, f.path(), f.path());
+    assert_eq!("fn banana() {}\n", &*session.load_file(f.path()));
+}
+
+#[test]
+fn file_cache_applies_ranged_edits_and_forgets_closed_buffers() {
+    let f = TmpFile::new("fn apple() {}\n");
+    let cache = core::FileCache::new();
+    cache.cache_file_contents(f.path(), "fn apricot() {}\nfn main() {}\n");
+
+    assert!(cache.edit_file_contents(f.path(), 3, 10, "banana"));
+    // past the end of the buffer
+    assert!(!cache.edit_file_contents(f.path(), 3, 100, ""));
+    {
+        let session = core::Session::from_path(&cache, f.path(), f.path());
+        assert_eq!("fn banana() {}\nfn main() {}\n", &*session.load_file(f.path()));
+    }
+
+    cache.uncache_file(f.path());
+    let session = core::Session::from_path(&cache, f.path(), f.path());
+    assert_eq!("fn apple() {}\n", &*session.load_file(f.path()));
+}
+
+#[test]
+fn file_cache_reset_rereads_files_but_keeps_buffers() {
+    let f = TmpFile::new("fn apple() {}\n");
+    let buf = TmpFile::new("");
+    let cache = core::FileCache::new();
+    cache.cache_file_contents(buf.path(), "// a\nfn cherry() {}\n");
+    {
+        let session = core::Session::from_path(&cache, f.path(), f.path());
+        assert_eq!("fn apple() {}\n", &*session.load_file(f.path()));
+        assert_eq!("     fn cherry() {}\n", &*session.load_file_and_mask_comments(buf.path()));
+    }
+
+    File::create(f.path()).unwrap().write_all(b"fn banana() {}\n").unwrap();
+
+    cache.reset();
+    let session = core::Session::from_path(&cache, f.path(), f.path());
+    assert_eq!("fn banana() {}\n", &*session.load_file(f.path()));
+    assert_eq!("// a\nfn cherry() {}\n", &*session.load_file(buf.path()));
+    assert_eq!("     fn cherry() {}\n", &*session.load_file_and_mask_comments(buf.path()));
+}
+
+#[test]
+fn completes_from_substituted_module_files() {
+    let src = "
+    mod foo;
+    mod bar;
+
+    fn main() {
+        foo::ap
+        bar::ba
+    }
+    ";
+
+    let dir = TmpDir::new();
+    let main = dir.new_temp_file_with_name("main.rs", src);
+    let _foo = dir.new_temp_file_with_name("foo.rs", "pub fn apple() {}\n");
+    let _bar = dir.new_temp_file_with_name("bar.rs", "pub fn banana() {}\n");
+    let unsaved_foo = TmpFile::new("pub fn apricot() {}\n");
+
+    let cache = core::FileCache::new();
+    let mut session = core::Session::from_path(&cache, main.path(), main.path());
+    session.substitute_file(&dir.pathbuf().join("foo.rs"), unsaved_foo.path());
+    session.substitute_contents(&dir.pathbuf().join("bar.rs"), "pub fn bagel() {}\n");
+
+    let pos = scopes::coords_to_point(src, 6, 15);
+    let got = complete_from_file(src, main.path(), pos, &session).nth(0).unwrap();
+    assert_eq!("apricot", got.matchstr);
+
+    let pos = scopes::coords_to_point(src, 7, 15);
+    let got = complete_from_file(src, main.path(), pos, &session).nth(0).unwrap();
+    assert_eq!("bagel", got.matchstr);
+}
+
+#[test]
+fn finds_type_at_point_including_generic_args() {
+    let src = "
+    struct Foo<T> { pub inner: T }
+    struct Bar;
+    fn main() {
+        let f: Foo<Bar> = Foo { inner: Bar };
+        f.inner;
+    }
+    ";
+
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, &path, &path);
+
+    let pos = scopes::coords_to_point(src, 6, 9);
+    let ty = core::find_type_at(src, &path, pos, &session).unwrap();
+    assert_eq!("Foo<Bar>", ty.to_string());
+    assert_eq!("struct Foo<T> { pub inner: T }", ty.defining_match().unwrap().contextstr);
+
+    let pos = scopes::coords_to_point(src, 6, 12);
+    let ty = core::find_type_at(src, &path, pos, &session).unwrap();
+    assert_eq!("Bar", ty.to_string());
+}
+
+#[test]
+fn finds_type_at_point_of_a_function() {
+    let src = "
+    fn apple(x: u32) -> u32 { x }
+    fn main() {
+        apple(3);
+    }
+    ";
+
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, &path, &path);
+
+    let pos = scopes::coords_to_point(src, 4, 10);
+    let ty = core::find_type_at(src, &path, pos, &session).unwrap();
+    assert_eq!("fn apple(x: u32) -> u32 { x }", ty.defining_match().unwrap().contextstr);
+}
+
+#[test]
+fn finds_references_across_crate_files() {
+    use racer::references::{find_references, ReferenceKind};
+
+    let libsrc = "
+    mod foo;
+    pub fn apple() -> u32 { 1 }
+    fn main() {
+        let mut x = apple();
+        x += 1;
+    }
+    ";
+    let foosrc = "
+    use super::apple;
+    pub fn use_apple() { apple(); }
+    ";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let foo = dir.new_temp_file_with_name("foo.rs", foosrc);
+    // no mod declares this file, so it isn't part of the crate
+    let _unlinked = dir.new_temp_file_with_name("unlinked.rs", foosrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, lib.path(), lib.path());
+
+    let pos = scopes::coords_to_point(libsrc, 5, 22);
+    let (def, refs) = find_references(libsrc, lib.path(), pos, &session).unwrap();
+    assert_eq!("apple", def.matchstr);
+    let got: Vec<_> = refs.iter().map(|r| (r.filepath.clone(), r.point, r.kind)).collect();
+    assert_eq!(vec![(foo.path().to_path_buf(), scopes::coords_to_point(foosrc, 2, 15), ReferenceKind::Read),
+                    (foo.path().to_path_buf(), scopes::coords_to_point(foosrc, 3, 25), ReferenceKind::Read),
+                    (lib.path().to_path_buf(), scopes::coords_to_point(libsrc, 3, 11), ReferenceKind::Definition),
+                    (lib.path().to_path_buf(), scopes::coords_to_point(libsrc, 5, 20), ReferenceKind::Read)],
+               got);
+
+    let pos = scopes::coords_to_point(libsrc, 6, 8);
+    let (_, refs) = find_references(libsrc, lib.path(), pos, &session).unwrap();
+    let kinds: Vec<_> = refs.iter().map(|r| r.kind).collect();
+    assert_eq!(vec![ReferenceKind::Definition, ReferenceKind::Write], kinds);
+}
+
+#[test]
+fn finds_signature_help_for_method_call() {
+    use racer::signature::find_signature_help;
+
+    let src = "
+    struct Foo;
+    impl Foo {
+        /// Moves the foo
+        ///
+        /// * `dx` - how far to move along x
+        pub fn shift(&mut self, dx: u32, dy: (u8, u8)) {}
+    }
+    fn main() {
+        let mut f = Foo;
+        f.shift(1, (2, 3
+    }
+    ";
+
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, &path, &path);
+
+    let pos = scopes::coords_to_point(src, 11, 24);
+    let sig = find_signature_help(src, &path, pos, &session).unwrap();
+    assert_eq!("shift", sig.name);
+    assert_eq!("pub fn shift(&mut self, dx: u32, dy: (u8, u8))", sig.signature);
+    assert_eq!(1, sig.active_arg);
+    assert_eq!(2, sig.args.len());
+    assert_eq!("dx", sig.args[0].name);
+    assert_eq!("u32", sig.args[0].ty);
+    assert_eq!("`dx` - how far to move along x", sig.args[0].docs);
+    assert_eq!("(u8, u8)", sig.args[1].ty);
+    assert_eq!("", sig.args[1].docs);
+}
+
+#[test]
+fn outlines_items_of_a_file_as_a_tree() {
+    use racer::outline::outline;
+
+    let src = "
+    pub mod foo {
+        pub struct Bar { pub a: u32, b: Baz }
+    }
+    enum Baz { One(u8), Two { x: u32 } }
+    impl Baz {
+        pub fn new() -> Baz { let x = ; }
+    }
+    macro_rules! mac { () => () }
+    ";
+
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, &path, &path);
+
+    let items = outline(&path, &session);
+    let names: Vec<_> = items.iter().map(|i| (&i.name[..], i.mtype.clone())).collect();
+    assert_eq!(vec![("foo", core::MatchType::Module), ("Baz", core::MatchType::Enum),
+                    ("Baz", core::MatchType::Impl), ("mac", core::MatchType::Macro)], names);
+
+    let bar = &items[0].children[0];
+    assert_eq!("Bar", bar.name);
+    assert!(bar.public);
+    assert_eq!((3, 19), scopes::point_to_coords(src, bar.point));
+    assert_eq!((3, 8), scopes::point_to_coords(src, bar.start));
+    assert_eq!((3, 45), scopes::point_to_coords(src, bar.end));
+    assert_eq!(vec![("a", true), ("b", false)],
+               bar.children.iter().map(|c| (&c.name[..], c.public)).collect::<Vec<_>>());
+
+    let variants = &items[1].children;
+    assert_eq!("0", variants[0].children[0].name);
+    assert_eq!("x", variants[1].children[0].name);
+
+    // the body of new doesn't parse, but the method is still listed
+    let new = &items[2].children[0];
+    assert_eq!("new", new.name);
+    assert_eq!(core::MatchType::Function, new.mtype);
+    assert!(new.public);
+}
+
+#[test]
+fn finds_workspace_symbols_in_module_files_ranked_by_match() {
+    use racer::symbols::workspace_symbols;
+
+    let libsrc = "
+    mod foo;
+    pub struct Apple;
+    fn pineapple() {}
+    ";
+    let foosrc = "
+    pub fn apple() {}
+    pub fn apple_pie() {}
+    fn orange() {}
+    ";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let foo = dir.new_temp_file_with_name("foo.rs", foosrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, foo.path(), foo.path());
+
+    let got: Vec<_> = workspace_symbols("apple", foo.path(), false, &session).into_iter()
+        .map(|m| (m.matchstr, m.filepath))
+        .collect();
+    assert_eq!(vec![("apple".to_owned(), foo.path().to_path_buf()),
+                    ("Apple".to_owned(), lib.path().to_path_buf()),
+                    ("apple_pie".to_owned(), foo.path().to_path_buf()),
+                    ("pineapple".to_owned(), lib.path().to_path_buf())],
+               got);
+}
+
+#[test]
+fn renames_across_files_and_refuses_collisions() {
+    use racer::rename::{rename, Edit, RenameError};
+
+    let libsrc = "
+    mod foo;
+    use foo::apple as pear;
+    fn main() {
+        let x = foo::apple();
+        let y = pear();
+        x + y
+    }
+    ";
+    let foosrc = "
+    pub fn apple() -> u32 { 1 }
+    pub fn orange() -> u32 { apple() }
+    ";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let foo = dir.new_temp_file_with_name("foo.rs", foosrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, foo.path(), foo.path());
+
+    let edit = |path: &Path, src: &str, line, col| {
+        let start = scopes::coords_to_point(src, line, col);
+        Edit { filepath: path.to_path_buf(), start: start, end: start + 5, replacement: "plum".to_owned() }
+    };
+
+    // the alias is left alone
+    let pos = scopes::coords_to_point(foosrc, 2, 14);
+    let edits = rename(foosrc, foo.path(), pos, "plum", &session).unwrap();
+    assert_eq!(vec![edit(foo.path(), foosrc, 2, 11),
+                    edit(foo.path(), foosrc, 3, 29),
+                    edit(lib.path(), libsrc, 3, 13),
+                    edit(lib.path(), libsrc, 5, 21)],
+               edits);
+
+    match rename(foosrc, foo.path(), pos, "orange", &session) {
+        Err(RenameError::Collision(m)) => assert_eq!("orange", m.matchstr),
+        other => panic!("expected a collision, got {:?}", other)
+    }
+
+    // renaming the alias only touches the file that declares it
+    let pos = scopes::coords_to_point(libsrc, 6, 18);
+    let edits = rename(libsrc, lib.path(), pos, "plum", &session).unwrap();
+    let got: Vec<_> = edits.iter().map(|e| (e.filepath.clone(), e.start, e.end)).collect();
+    let at = |line, col| scopes::coords_to_point(libsrc, line, col);
+    assert_eq!(vec![(lib.path().to_path_buf(), at(3, 22), at(3, 26)),
+                    (lib.path().to_path_buf(), at(6, 16), at(6, 20))],
+               got);
+
+    // the alias's siblings are in the module that imports it, not the one defining apple
+    let pos = scopes::coords_to_point(libsrc, 6, 18);
+    assert!(rename(libsrc, lib.path(), pos, "orange", &session).is_ok());
+    match rename(libsrc, lib.path(), pos, "main", &session) {
+        Err(RenameError::Collision(m)) =>
+            assert_eq!(("main", lib.path()), (&m.matchstr[..], m.filepath.as_path())),
+        other => panic!("expected a collision, got {:?}", other)
+    }
+
+    // a local collides with another local in scope
+    let pos = scopes::coords_to_point(libsrc, 7, 9);
+    match rename(libsrc, lib.path(), pos, "y", &session) {
+        Err(RenameError::Collision(m)) => assert_eq!("y", m.matchstr),
+        other => panic!("expected a collision, got {:?}", other)
+    }
+}
+
+#[test]
+fn completes_fuzzy_abbreviations_best_first() {
+    use racer::core::fuzzy_complete_from_file;
+
+    let src = "
+    struct HashMap;
+    struct HashSet;
+    struct Foo;
+    impl Foo {
+        fn read_to_end(&self) {}
+        fn rate(&self) {}
+    }
+    fn main() {
+        let hmap = HashMap;
+        let f = Foo;
+        f.rte
+        hm
+    }";
+
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+
+    let pos = scopes::coords_to_point(src, 12, 13);
+    let got: Vec<_> = fuzzy_complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect();
+    assert_eq!(vec!["read_to_end", "rate"], got);
+
+    let pos = scopes::coords_to_point(src, 13, 10);
+    let got: Vec<_> = fuzzy_complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect();
+    assert_eq!(vec!["hmap", "HashMap"], got);
+}
+
+#[test]
+fn ranks_and_dedupes_completions_by_scope() {
+    let libsrc = "
+    mod other;
+    use other::{ver_other, Ver};
+    use other::ver_other;
+    struct Verbose;
+    fn ver_mod() {}
+    fn main() {
+        let ver_local = 1;
+        Ver
+    }
+    ";
+    let othersrc = "
+    pub fn ver_other() {}
+    pub struct Ver;
+    ";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let _other = dir.new_temp_file_with_name("other.rs", othersrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, lib.path(), lib.path());
+
+    // exact match first, then nearest scope first
+    let pos = scopes::coords_to_point(libsrc, 9, 11);
+    let got: Vec<_> = complete_from_file(libsrc, lib.path(), pos, &session).map(|m| m.matchstr).collect();
+    assert_eq!(vec!["Ver", "Verbose"], got);
+
+    // each item once, however many ways it's reached
+    let lowersrc = libsrc.replace("        Ver\n", "        ver\n");
+    let got: Vec<_> = complete_from_file(&lowersrc, lib.path(), pos, &session).map(|m| m.matchstr).collect();
+    assert_eq!(vec!["ver_local", "ver_mod", "ver_other"], got);
+}
+
+#[test]
+fn finds_docs_of_completions_and_definitions() {
+    let libsrc = "//! The crate docs
+mod other;
+/// Adds one
+///
+/// * `a` - the number
+#[inline]
+fn add_one(a: u32) -> u32 { a + 1 }
+/**
+ * A point
+ */
+#[derive(Debug,
+         Clone)]
+struct Point { x: u32 }
+#[doc = \"Doubles\"]
+fn double(a: u32) -> u32 { a * 2 }
+fn main() {
+    // not docs
+    let v = add_one(1);
+    other::foo();
+    add
+}
+";
+    let othersrc = "//! Other docs\n\n/// Foo docs\npub fn foo() {}\n";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let _other = dir.new_temp_file_with_name("other.rs", othersrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, lib.path(), lib.path());
+
+    let pos = scopes::coords_to_point(libsrc, 20, 7);
+    let got = complete_from_file(libsrc, lib.path(), pos, &session).nth(0).unwrap();
+    assert_eq!("add_one", got.matchstr);
+    // completions leave the docs to be filled in for the items shown
+    assert_eq!("", got.docs);
+    assert_eq!("Adds one\n\n* `a` - the number", docs::fill_docs(got, &session).docs);
+
+    let docs_at = |line, col| {
+        let pos = scopes::coords_to_point(libsrc, line, col);
+        find_definition(libsrc, lib.path(), pos, &session).unwrap().docs
+    };
+    assert_eq!("A point", docs_at(13, 8));
+    assert_eq!("Doubles", docs_at(15, 4));
+    assert_eq!("Foo docs", docs_at(19, 12));
+    assert_eq!("Other docs", docs_at(19, 6));
+    assert_eq!("", docs_at(7, 29));
+}
+
+#[test]
+fn finds_name_and_item_ranges_of_definitions() {
+    let libsrc = "mod other;
+pub struct Point {
+    pub x: HashMap<u8, u8>,
+    y: u32,
+}
+enum Shape {
+    Circle(Point, u32),
+    Square { side: u32 },
+}
+fn area(p: Point) -> u32 {
+    p.y
+}
+fn main() {
+    area(Point { x: HashMap::new(), y: 0 });
+    Shape::Circle;
+    other::foo();
+}
+";
+    let othersrc = "pub fn foo() {}\n";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let _other = dir.new_temp_file_with_name("other.rs", othersrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, lib.path(), lib.path());
+
+    let ranges_at = |line, col| {
+        let pos = scopes::coords_to_point(libsrc, line, col);
+        let m = find_definition(libsrc, lib.path(), pos, &session).unwrap();
+        let src = session.load_file(&m.filepath);
+        (src[m.name_range.0..m.name_range.1].to_owned(),
+         src[m.item_range.0..m.item_range.1].to_owned())
+    };
+    assert_eq!(("area".to_owned(), "fn area(p: Point) -> u32 {\n    p.y\n}".to_owned()),
+               ranges_at(14, 6));
+    assert_eq!(("y".to_owned(), "y: u32".to_owned()), ranges_at(11, 6));
+    assert_eq!(("Circle".to_owned(), "Circle(Point, u32)".to_owned()), ranges_at(15, 15));
+    assert_eq!(("".to_owned(), othersrc.to_owned()), ranges_at(16, 6));
+    assert_eq!(("p".to_owned(), "p".to_owned()), ranges_at(11, 5));
+}
+
+#[test]
+fn finds_definition_at_encoded_coords_on_non_ascii_lines() {
+    use racer::core::Encoding;
+
+    let src = "fn frobnicate() {}\nfn main() {\n    let s = \"é𝄞\"; frobnicate();\n}\n";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, &path, &path);
+
+    // the 'n' of the frobnicate call, counted in bytes, chars and utf-16 code units
+    let call = src.rfind("frobnicate").unwrap() + 1;
+    for &(encoding, col) in &[(Encoding::Bytes, 23), (Encoding::Chars, 19), (Encoding::Utf16, 20)] {
+        let pos = scopes::encoded_coords_to_point(src, 3, col, encoding);
+        assert_eq!(call, pos);
+        assert_eq!((3, col), scopes::point_to_encoded_coords(src, pos, encoding));
+        let got = find_definition(src, &path, pos, &session).unwrap();
+        assert_eq!("frobnicate", got.matchstr);
+        assert_eq!((1, 3), scopes::point_to_encoded_coords(src, got.point, encoding));
+    }
+}
+
+#[test]
+fn completes_struct_literal_fields_not_yet_given() {
+    let src = "
+    struct Config { verbose: bool, threads: u32, name: String }
+    enum Shape { Circle { radius: f64, center: (f64, f64) }, Square(f64) }
+    impl Config {
+        fn new() -> Config {
+            Self { verbose: false, th }
+        }
+    }
+    fn main() {
+        let c = Config { name: String::new(),  };
+        let s = Shape::Circle { ra };
+    }";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+    let names = |line, col| complete_from_file(src, path, scopes::coords_to_point(src, line, col), &session)
+        .map(|m| (m.matchstr, m.contextstr)).collect::<Vec<_>>();
+
+    assert_eq!(vec![("verbose".to_owned(), "bool".to_owned()), ("threads".to_owned(), "u32".to_owned())],
+               names(10, 46));
+    assert_eq!(vec![("threads".to_owned(), "u32".to_owned())], names(6, 36));
+    assert_eq!(vec![("radius".to_owned(), "f64".to_owned())], names(11, 34));
+
+    let pos = scopes::coords_to_point(src, 6, 21);
+    let got = find_definition(src, path, pos, &session).unwrap();
+    assert_eq!("verbose", got.matchstr);
+    assert_eq!((2, 20), scopes::point_to_coords(src, got.point));
+}
+
+#[test]
+fn completes_variants_in_match_arms_and_fills_missing_arms() {
+    use racer::arms;
+
+    let src = "
+    enum Shape { Circle { radius: f64 }, Square(f64), Rect(f64, f64), Empty }
+    fn area(s: &Shape) -> f64 {
+        match *s {
+            Shape::Square(w) => w * w,
+            Re
+        }
+    }
+    fn perimeter(s: Shape) -> f64 {
+        match s { Shape::Empty | Shape::Circle { .. } => 0.0 }
+    }";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+
+    let pos = scopes::coords_to_point(src, 6, 14);
+    let got = complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect::<Vec<_>>();
+    assert_eq!(vec!["Rect".to_owned()], got);
+
+    let pos = scopes::coords_to_point(src, 4, 10);
+    let missing = arms::fill_match(src, path, pos, &session).unwrap();
+    assert_eq!("Shape", missing.enummatch.matchstr);
+    // `Re` isn't an arm yet
+    assert_eq!(vec!["Shape::Circle { radius: _ } => unimplemented!(),".to_owned(),
+                    "Shape::Rect(_, _) => unimplemented!(),".to_owned(),
+                    "Shape::Empty => unimplemented!(),".to_owned()], missing.arms);
+
+    let pos = scopes::coords_to_point(src, 10, 20);
+    let missing = arms::fill_match(src, path, pos, &session).unwrap();
+    assert_eq!(vec!["Shape::Square(_) => unimplemented!(),".to_owned(),
+                    "Shape::Rect(_, _) => unimplemented!(),".to_owned()], missing.arms);
+    let filled = format!("{}{}{}", &src[..missing.edit.start], missing.edit.replacement,
+                         &src[missing.edit.end..]);
+    assert!(filled.contains("match s { Shape::Empty | Shape::Circle { .. } => 0.0,
+            Shape::Square(_) => unimplemented!(),
+            Shape::Rect(_, _) => unimplemented!(),
+        }"));
+}
+
+#[test]
+fn only_bindings_that_arent_constants_are_catch_all_arms() {
+    use racer::arms;
+
+    let src = "
+    enum Dir { Up, Down }
+    #[allow(non_upper_case_globals)]
+    const up: Dir = Dir::Up;
+    fn flip(d: Dir) -> Dir {
+        match d { up => Dir::Down }
+    }
+    fn keep(d: Dir) -> Dir {
+        match d { ref other => Dir::Up }
+    }";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+
+    let pos = scopes::coords_to_point(src, 6, 10);
+    let missing = arms::fill_match(src, path, pos, &session).unwrap();
+    assert_eq!(vec!["Dir::Up => unimplemented!(),".to_owned(),
+                    "Dir::Down => unimplemented!(),".to_owned()], missing.arms);
+
+    let pos = scopes::coords_to_point(src, 9, 10);
+    let missing = arms::fill_match(src, path, pos, &session).unwrap();
+    assert!(missing.arms.is_empty());
+}
+
+#[test]
+fn completes_methods_from_impls_in_other_modules() {
+    let libsrc = "mod shapes;
+mod ext;
+mod other {
+    pub struct Circle;
+}
+use shapes::Circle;
+fn main() {
+    let c = Circle::unit();
+    c.gr
+    Circle::un
+}
+";
+    let shapessrc = "pub struct Circle { pub radius: f64 }
+impl Circle {
+    pub fn area(&self) -> f64 { 3.14 * self.radius * self.radius }
+}
+";
+    let extsrc = "use shapes::Circle;
+impl Circle {
+    pub fn grow(&mut self) {}
+    pub fn unit() -> Circle { Circle { radius: 1.0 } }
+}
+impl ::other::Circle {
+    pub fn grow_other(&self) {}
+}
+";
+
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", libsrc);
+    let _shapes = dir.new_temp_file_with_name("shapes.rs", shapessrc);
+    let _ext = dir.new_temp_file_with_name("ext.rs", extsrc);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, lib.path(), lib.path());
+
+    let pos = scopes::coords_to_point(libsrc, 9, 8);
+    let got = complete_from_file(libsrc, lib.path(), pos, &session).collect::<Vec<_>>();
+    // not grow_other, which is a method of the other Circle
+    assert_eq!(vec!["grow"], got.iter().map(|m| &m.matchstr[..]).collect::<Vec<_>>());
+    assert!(got[0].filepath.ends_with("ext.rs"));
+
+    let pos = scopes::coords_to_point(libsrc, 10, 14);
+    let got = complete_from_file(libsrc, lib.path(), pos, &session).nth(0).unwrap();
+    assert_eq!("unit", got.matchstr);
+}
+
+#[test]
+fn completes_methods_of_generic_params_through_their_bounds() {
+    let src = "
+trait Read { fn read(&mut self, buf: &mut [u8]) -> usize; }
+trait BufRead: Read { fn read_line(&mut self) -> String { String::new() } }
+trait Debug { fn fmt_debug(&self); }
+trait Iterator { fn next(&mut self) -> Option<u8>; }
+fn f<T: BufRead + Debug>(t: T) {
+    t.re
+}
+fn g<I>(i: &mut I) where I: Iterator {
+    i.ne
+}
+";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+
+    let pos = scopes::coords_to_point(src, 7, 8);
+    let mut got = complete_from_file(src, path, pos, &session)
+        .map(|m| m.matchstr).collect::<Vec<_>>();
+    got.sort();
+    // read comes from the supertrait
+    assert_eq!(vec!["read", "read_line"], got);
+
+    let pos = scopes::coords_to_point(src, 10, 8);
+    let got = complete_from_file(src, path, pos, &session).nth(0).unwrap();
+    assert_eq!("next", got.matchstr);
+}
+
+#[test]
+fn completes_trait_default_methods_and_blanket_impl_methods() {
+    let src = "
+pub trait Display { fn fmt(&self) -> String; }
+pub trait ToString { fn to_string(&self) -> String; }
+impl<T: Display + ?Sized> ToString for T {
+    fn to_string(&self) -> String { self.fmt() }
+}
+pub trait Iter {
+    fn next(&mut self) -> Option<u8>;
+    fn count(self) -> usize { 0 }
+    fn filter(self) -> Self { self }
+}
+struct Foo;
+impl Iter for Foo {
+    fn next(&mut self) -> Option<u8> { None }
+    fn filter(self) -> Self { self }
+}
+impl Display for Foo {
+    fn fmt(&self) -> String { String::new() }
+}
+struct Bar;
+fn main() {
+    let f = Foo;
+    f.
+    f.count();
+    let b = Bar;
+    b.to_
+}
+";
+    let dir = TmpDir::new();
+    let lib = dir.new_temp_file_with_name("lib.rs", src);
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, lib.path(), lib.path());
+
+    let pos = scopes::coords_to_point(src, 23, 6);
+    let got = complete_from_file(src, lib.path(), pos, &session)
+        .map(|m| (m.matchstr, scopes::point_to_coords(src, m.point).0)).collect::<Vec<_>>();
+    // each method once, the impl's where it overrides the trait's
+    assert_eq!(vec![("fmt".to_owned(), 18), ("next".to_owned(), 14), ("count".to_owned(), 9),
+                    ("filter".to_owned(), 15), ("to_string".to_owned(), 5)], got);
+
+    let pos = scopes::coords_to_point(src, 24, 8);
+    let got = find_definition(src, lib.path(), pos, &session).unwrap();
+    assert_eq!("count", got.matchstr);
+    assert_eq!(9, scopes::point_to_coords(src, got.point).0);
+
+    // Bar isn't Display, so isn't ToString either
+    let pos = scopes::coords_to_point(src, 26, 9);
+    assert_eq!(0, complete_from_file(src, lib.path(), pos, &session).count());
+}
+
+#[test]
+fn completes_methods_of_std_blanket_impls() {
+    // a project whose std is a local crate laid out like the real one: ToString and its blanket
+    // impl for Display types are in `string` and reach the user's code through the prelude
+    let dir = TmpDir::new();
+    let root = dir.pathbuf().clone();
+    let _toml = TmpFile::with_path(root.join("Cargo.toml"), "
+[package]
+name = \"app\"
+version = \"0.1.0\"
+
+[dependencies]
+std = { path = \"std\" }
+");
+    let stdsrc = TmpDir::with_name(root.join("std").join("src").to_str().unwrap());
+    let _stdtoml = TmpFile::with_path(root.join("std").join("Cargo.toml"),
+                                      "[package]\nname = \"std\"\nversion = \"0.1.0\"\n");
+    let _stdlib = stdsrc.new_temp_file_with_name("lib.rs", "
+pub mod fmt;
+pub mod string;
+pub mod prelude {
+    pub mod v1 {
+        pub use string::{String, ToString};
+    }
+}
+");
+    let _fmt = stdsrc.new_temp_file_with_name("fmt.rs", "
+pub struct Formatter;
+pub trait Display {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), ()>;
+}
+");
+    let _string = stdsrc.new_temp_file_with_name("string.rs", "
+use fmt;
+
+pub struct String;
+
+pub trait ToString {
+    fn to_string(&self) -> String;
+}
+
+impl<T: fmt::Display + ?Sized> ToString for T {
+    #[inline]
+    fn to_string(&self) -> String {
+        String
+    }
+}
+");
+
+    let src = "
+use std::fmt;
+struct Foo;
+impl fmt::Display for Foo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), ()> { Ok(()) }
+}
+struct Bar;
+fn main() {
+    let f = Foo;
+    f.to_s
+    let b = Bar;
+    b.to_s
+}
+";
+    let f = TmpFile::with_path(root.join("lib.rs"), src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+
+    let pos = scopes::coords_to_point(src, 10, 10);
+    let got = complete_from_file(src, path, pos, &session).nth(0).unwrap();
+    assert_eq!("to_string", got.matchstr);
+    assert!(got.filepath.ends_with("string.rs"));
+
+    // Bar isn't Display, so isn't ToString either
+    let pos = scopes::coords_to_point(src, 12, 10);
+    assert_eq!(0, complete_from_file(src, path, pos, &session).count());
+}
+
+#[test]
+fn completes_methods_of_trait_objects_and_impl_trait_values() {
+    let src = "
+pub enum Option<T> { Some(T), None }
+impl<T> Option<T> {
+    pub fn unwrap(self) -> T { unimplemented!() }
+}
+pub trait Write { fn write_all(&mut self, buf: &[u8]); }
+pub trait Iterator {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+    fn count(self) -> usize { 0 }
+}
+pub trait DoubleEnded: Iterator { fn next_back(&mut self) -> Option<Self::Item>; }
+pub struct Point { pub x: i32, pub y: i32 }
+fn points() -> impl Iterator<Item = Point> { unimplemented!() }
+fn f(w: &mut dyn Write, it: &mut dyn DoubleEnded<Item = Point>, b: &Write) {
+    w.wr
+    it.next_back().unwrap().
+    points().c
+    points().next().unwrap().
+    b.
+}
+";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+    let complete = |line, col| {
+        let pos = scopes::coords_to_point(src, line, col);
+        complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["write_all"], complete(16, 8));
+    assert_eq!(vec!["write_all"], complete(20, 6));
+    // the Item binding flows through next_back, from the supertrait, and unwrap
+    assert_eq!(vec!["x", "y"], complete(17, 28));
+    assert_eq!(vec!["count"], complete(18, 14));
+    assert_eq!(vec!["x", "y"], complete(19, 29));
+}
+
+#[test]
+fn resolves_associated_types_from_impls() {
+    let src = "
+pub enum Option<T> { Some(T), None }
+impl<T> Option<T> {
+    pub fn unwrap(self) -> T { unimplemented!() }
+}
+pub trait Iterator {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+    fn last(self) -> Option<Self::Item> { None }
+}
+pub struct Point { pub x: i32, pub y: i32 }
+pub struct Counter;
+impl Iterator for Counter {
+    type Item = Point;
+    fn next(&mut self) -> Option<Self::Item> {
+        let p: Self::Item = Point { x: 1, y: 2 };
+        p.
+    }
+}
+fn f(c: Counter) {
+    c.next().unwrap().
+}
+fn g(c: Counter) {
+    c.last().unwrap().
+}
+fn h(q: <Counter as Iterator>::Item) {
+    q.
+}
+";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+    let complete = |line, col| {
+        let pos = scopes::coords_to_point(src, line, col);
+        complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["x", "y"], complete(17, 10));
+    assert_eq!(vec!["x", "y"], complete(21, 22));
+    // a default method of the trait, whose Self::Item is the impl's
+    assert_eq!(vec!["x", "y"], complete(24, 22));
+    assert_eq!(vec!["x", "y"], complete(27, 6));
+}
+
+#[test]
+fn completes_indexed_values_and_tuple_fields() {
+    let src = "
+pub trait Index<Idx> {
+    type Output;
+    fn index(&self, index: Idx) -> &Self::Output;
+}
+pub struct Point { pub x: i32, pub y: i32 }
+pub struct Line(pub Point, pub Point);
+pub struct Table<K, V> { k: K, v: V }
+impl<'a, K, V> Index<&'a str> for Table<K, V> {
+    type Output = V;
+    fn index(&self, key: &str) -> &V { &self.v }
+}
+fn f(t: Table<i32, Point>, arr: [Point; 2], s: &[Line]) {
+    t[\"k\"].
+}
+fn g(t: Table<i32, Point>, arr: [Point; 2], s: &[Line]) {
+    arr[1].
+}
+fn h(t: Table<i32, Point>, arr: [Point; 2], s: &[Line]) {
+    s[0].1.
+}
+fn i(t: Table<i32, Point>) {
+    let pair = (t, Point { x: 1, y: 2 });
+    pair.
+}
+fn j(t: Table<i32, Point>) {
+    let pair = (t, Point { x: 1, y: 2 });
+    pair.1.
+}
+";
+    let f = TmpFile::new(src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+    let complete = |line, col| {
+        let pos = scopes::coords_to_point(src, line, col);
+        complete_from_file(src, path, pos, &session).collect::<Vec<_>>()
+    };
+    let names = |line, col| complete(line, col).into_iter().map(|m| m.matchstr).collect::<Vec<_>>();
+
+    assert_eq!(vec!["x", "y"], names(14, 11));
+    assert_eq!(vec!["x", "y"], names(17, 11));
+    assert_eq!(vec!["x", "y"], names(20, 11));
+    let fields = complete(24, 9);
+    assert_eq!(vec!["0", "1"], fields.iter().map(|m| &m.matchstr[..]).collect::<Vec<_>>());
+    assert_eq!("Point", fields[1].contextstr);
+    assert_eq!(vec!["x", "y"], names(28, 11));
+}
+
+#[test]
+fn completes_methods_of_primitive_types_and_literals() {
+    // a project whose std and core are local crates with the inherent impls of the primitives
+    let dir = TmpDir::new();
+    let root = dir.pathbuf().clone();
+    let _toml = TmpFile::with_path(root.join("Cargo.toml"), "
+[package]
+name = \"app\"
+version = \"0.1.0\"
+
+[dependencies]
+std = { path = \"std\" }
+core = { path = \"core\" }
+");
+    let stdsrc = TmpDir::with_name(root.join("std").join("src").to_str().unwrap());
+    let _stdtoml = TmpFile::with_path(root.join("std").join("Cargo.toml"),
+                                      "[package]\nname = \"std\"\nversion = \"0.1.0\"\n");
+    let _stdlib = stdsrc.new_temp_file_with_name("lib.rs", "
+pub mod str {
+    impl str {
+        pub fn len(&self) -> usize { 0 }
+    }
+}
+pub mod char {
+    impl char {
+        pub fn is_alphabetic(self) -> bool { false }
+    }
+}
+pub mod f64 {
+    impl f64 {
+        pub fn floor(self) -> f64 { self }
+    }
+}
+");
+    let coresrc = TmpDir::with_name(root.join("core").join("src").to_str().unwrap());
+    let _coretoml = TmpFile::with_path(root.join("core").join("Cargo.toml"),
+                                       "[package]\nname = \"core\"\nversion = \"0.1.0\"\n");
+    let _corelib = coresrc.new_temp_file_with_name("lib.rs", "
+pub mod num {
+    impl i32 {
+        pub fn abs(self) -> i32 { self }
+    }
+    impl u8 {
+        pub fn is_ascii(&self) -> bool { false }
+    }
+    impl u64 {
+        pub fn count_ones(self) -> u32 { 0 }
+    }
+}
+");
+
+    let src = "
+fn a() {
+    \"abc\".
+}
+fn b() {
+    1u8.
+}
+fn c() {
+    1.0.
+}
+fn d() {
+    let x: u64 = 0;
+    x.
+}
+fn e() {
+    let c = 'a';
+    c.
+}
+fn f() {
+    5.
+}
+";
+    let f = TmpFile::with_path(root.join("lib.rs"), src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+    let complete = |line, col| {
+        let pos = scopes::coords_to_point(src, line, col);
+        complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["len"], complete(3, 10));
+    assert_eq!(vec!["is_ascii"], complete(6, 8));
+    // unsuffixed literals default to f64 and i32
+    assert_eq!(vec!["floor"], complete(9, 8));
+    assert_eq!(vec!["count_ones"], complete(13, 6));
+    assert_eq!(vec!["is_alphabetic"], complete(17, 6));
+    assert_eq!(vec!["abs"], complete(20, 6));
+}
+
+#[test]
+fn completes_integer_methods_defined_by_macros_in_libcore() {
+    // libcore's integer methods are in int_impl!/uint_impl! invocations in the inherent impls
+    let dir = TmpDir::new();
+    let root = dir.pathbuf().clone();
+    let _toml = TmpFile::with_path(root.join("Cargo.toml"), "
+[package]
+name = \"app\"
+version = \"0.1.0\"
+
+[dependencies]
+core = { path = \"core\" }
+");
+    let coresrc = TmpDir::with_name(root.join("core").join("src").to_str().unwrap());
+    let _coretoml = TmpFile::with_path(root.join("core").join("Cargo.toml"),
+                                       "[package]\nname = \"core\"\nversion = \"0.1.0\"\n");
+    let _corelib = coresrc.new_temp_file_with_name("lib.rs", "pub mod num;\n");
+    let numdir = TmpDir::with_name(root.join("core").join("src").join("num").to_str().unwrap());
+    let numsrc = "
+// `Int` + `SignedInt` implemented for signed integers
+macro_rules! int_impl {
+    ($ActualT:ty, $UnsignedT:ty, $BITS:expr,
+     $add_with_overflow:path,
+     $sub_with_overflow:path) => {
+        /// Returns the smallest value that can be represented by this integer type.
+        #[stable(feature = \"rust1\", since = \"1.0.0\")]
+        #[inline]
+        pub fn min_value() -> Self {
+            (-1 as Self) << ($BITS - 1)
+        }
+
+        /// Raises self to the power of `exp`, using exponentiation by squaring.
+        #[stable(feature = \"rust1\", since = \"1.0.0\")]
+        #[inline]
+        pub fn pow(self, mut exp: u32) -> Self {
+            let mut base = self;
+            let mut acc = Self::one();
+            while exp > 1 {
+                exp /= 2;
+                base = base * base;
+            }
+            acc
+        }
+    }
+}
+
+// `Int` + `UnsignedInt` implemented for unsigned integers
+macro_rules! uint_impl {
+    ($ActualT:ty, $BITS:expr,
+     $ctpop:path) => {
+        /// Returns the number of ones in the binary representation of `self`.
+        #[stable(feature = \"rust1\", since = \"1.0.0\")]
+        #[inline]
+        pub fn count_ones(self) -> u32 {
+            unsafe { $ctpop(self as $ActualT) as u32 }
+        }
+    }
+}
+
+#[lang = \"i32\"]
+impl i32 {
+    int_impl! { i32, u32, 32,
+        intrinsics::i32_add_with_overflow,
+        intrinsics::i32_sub_with_overflow }
+}
+
+#[lang = \"u8\"]
+impl u8 {
+    uint_impl! { u8, 8,
+        intrinsics::ctpop8 }
+}
+";
+    let _num = numdir.new_temp_file_with_name("mod.rs", numsrc);
+
+    let src = "
+fn a() {
+    5.po
+}
+fn b() {
+    let x: u8 = 1;
+    x.count_o
+}
+";
+    let f = TmpFile::with_path(root.join("lib.rs"), src);
+    let path = f.path();
+    let cache = core::FileCache::new();
+    let session = core::Session::from_path(&cache, path, path);
+
+    let pos = scopes::coords_to_point(src, 3, 8);
+    let got = complete_from_file(src, path, pos, &session).collect::<Vec<_>>();
+    assert_eq!(vec!["pow"], got.iter().map(|m| &m.matchstr[..]).collect::<Vec<_>>());
+    // the method is found in the macro's body
+    assert_eq!(numsrc.find("fn pow").unwrap() + 3, got[0].point);
+
+    let pos = scopes::coords_to_point(src, 7, 13);
+    let got = complete_from_file(src, path, pos, &session).map(|m| m.matchstr).collect::<Vec<_>>();
+    assert_eq!(vec!["count_ones"], got);
+}